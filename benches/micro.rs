@@ -5,10 +5,10 @@ use easybench::bench_env;
 use idsp::{atan2, cossin, iir, iir_int, Lowpass, PLL, RPLL};
 
 fn atan2_bench() {
-    let xi = (10 << 16) as i32;
+    let xi = 10 << 16;
     let xf = xi as f32 / i32::MAX as f32;
 
-    let yi = (-26_328 << 16) as i32;
+    let yi = -26_328 << 16;
     let yf = yi as f32 / i32::MAX as f32;
 
     println!(
@@ -57,7 +57,12 @@ fn iir_int_bench() {
     let mut xy = iir_int::Vec5::default();
     println!(
         "int_iir::IIR::update(s, x): {}",
-        bench_env(0x2832, |x| dut.update(&mut xy, *x))
+        bench_env(0x2832, |x| dut.update(&mut xy, *x, false))
+    );
+    let mut xy = [iir_int::Vec5::default(); 4];
+    println!(
+        "int_iir::IIR::update_interleaved(s, [x; 32]): {}",
+        bench_env([0x2832; 32], |x| dut.update_interleaved(&mut xy, x, false))
     );
 }
 
@@ -68,6 +73,10 @@ fn iir_f32_bench() {
         "int::IIR::<f32>::update(s, x): {}",
         bench_env(0.32241, |x| dut.update(&mut xy, *x, true))
     );
+    println!(
+        "int::IIR::<f32>::update_block(s, [x; 32]): {}",
+        bench_env([0.32241; 32], |x| dut.update_block(&mut xy, x, true))
+    );
 }
 
 fn iir_f64_bench() {
@@ -97,8 +97,8 @@ mod tests {
         const N: usize = 321;
         let mut test_vals = [0i32; N + 4];
         let scale = (1i64 << 31) as f64;
-        for i in 0..N {
-            test_vals[i] = (scale * (-1. + 2. * i as f64 / N as f64)) as i32;
+        for (i, x) in test_vals[..N].iter_mut().enumerate() {
+            *x = (scale * (-1. + 2. * i as f64 / N as f64)) as i32;
         }
 
         assert!(test_vals.contains(&i32::MIN));
@@ -1,9 +1,24 @@
-use miniconf::MiniconfAtomic;
+use miniconf::{Miniconf, MiniconfAtomic};
 use serde::{Deserialize, Serialize};
 
-use super::{abs, copysign, macc};
+use super::{abs, copysign, macc, Complex, Slew, SlewSample};
+
+mod analog;
+mod autotune;
+pub mod design;
+mod iirn;
+mod response;
+mod spec;
+mod state_space;
+pub use analog::*;
+pub use autotune::*;
+use core::f64::consts::{LN_2, PI};
 use core::iter::Sum;
-use num_traits::{clamp, Float, NumCast};
+pub use iirn::*;
+use num_traits::{Float, NumCast};
+pub use response::*;
+pub use spec::*;
+pub use state_space::*;
 
 /// IIR state and coefficients type.
 ///
@@ -15,6 +30,23 @@ use num_traits::{clamp, Float, NumCast};
 /// (-a1, -a2), all five normalized such that a0 = 1.
 pub type Vec5<T> = [T; 5];
 
+/// Biquad filter shape (bandwidth or slope) specification.
+///
+/// See <https://webaudio.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>
+/// for the definitions.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Shape<T> {
+    /// Quality factor (`1/sqrt(2)` for critical damping of lowpass/highpass).
+    Q(T),
+    /// Bandwidth in octaves. For bandpass and notch filters between
+    /// the -3 dB frequencies, for peaking filters between the midpoint
+    /// (half gain in dB) frequencies.
+    Bandwidth(T),
+    /// Shelf slope. With `1` the slope is as steep as possible while
+    /// remaining monotonic. Only valid for shelf filters.
+    Slope(T),
+}
+
 /// IIR configuration.
 ///
 /// Contains the coeeficients `ba`, the output offset `y_offset`, and the
@@ -39,7 +71,8 @@ pub type Vec5<T> = [T; 5];
 ///   overall (DC feed-forward) gain of the filter.
 /// * It stores only previous outputs and inputs. These have direct and
 ///   invariant interpretation (independent of gains and offsets).
-///   Therefore it can trivially implement bump-less transfer.
+///   Therefore it can trivially implement bump-less transfer
+///   (see `state_for_output()`, `state_for_input()`, and `track()`).
 /// * Cascading multiple IIR filters allows stable and robust
 ///   implementation of transfer functions beyond bequadratic terms.
 ///
@@ -81,7 +114,7 @@ impl<T: Float + Default + Sum<T>> IIR<T> {
     /// * `kp` - Proportional gain. Also defines gain sign.
     /// * `ki` - Integral gain at Nyquist. Sign taken from `kp`.
     /// * `g` - Gain limit.
-    pub fn set_pi(&mut self, kp: T, ki: T, g: T) -> Result<(), &str> {
+    pub fn set_pi(&mut self, kp: T, ki: T, g: T) -> Result<(), &'static str> {
         let zero: T = T::default();
         let one: T = NumCast::from(1.0).unwrap();
         let two: T = NumCast::from(2.0).unwrap();
@@ -107,6 +140,395 @@ impl<T: Float + Default + Sum<T>> IIR<T> {
         Ok(())
     }
 
+    /// Configures IIR filter coefficients for proportional-integral-derivative
+    /// behavior with integral and derivative gain limits.
+    ///
+    /// The transfer function is the sum of the three actions:
+    ///
+    /// * P: `kp`
+    /// * I: `ki*(1 + z^-1)/((1 + ki/gi) - (1 - ki/gi)*z^-1)`, magnitude
+    ///   `ki/tan(pi*f)` well above the gain limit corner, `gi` at DC.
+    /// * D: `kd*(1 - z^-1)/((1 + kd/gd) + (1 - kd/gd)*z^-1)`, magnitude
+    ///   `kd*tan(pi*f)` well below the gain limit corner, `gd` at Nyquist.
+    ///
+    /// As with all other `IIR` configurations, set-point changes should be applied
+    /// through `y_offset` (see `set_x_offset()`) to avoid derivative kick and
+    /// anti-windup is inherent to the output clamping.
+    ///
+    /// # Arguments
+    ///
+    /// * `kp` - Proportional gain. Also defines gain sign.
+    /// * `ki` - Integral gain. Sign taken from `kp`.
+    /// * `kd` - Derivative gain. Sign taken from `kp`.
+    /// * `gi` - Integral gain limit. Zero for an unlimited integrator.
+    /// * `gd` - Derivative gain limit. Must be non-zero if `kd` is non-zero.
+    pub fn set_pid(&mut self, kp: T, ki: T, kd: T, gi: T, gd: T) -> Result<(), &'static str> {
+        let zero = T::zero();
+        let one = T::one();
+        let ki = copysign(ki, kp);
+        let kd = copysign(kd, kp);
+        let gi = copysign(gi, kp);
+        let gd = copysign(gd, kp);
+        let (bi, ai) = if abs(ki) < T::epsilon() {
+            ([zero; 2], [one, zero])
+        } else {
+            let e = if abs(gi) < T::epsilon() {
+                zero
+            } else {
+                ki / gi
+            };
+            ([ki, ki], [one + e, e - one])
+        };
+        let (bd, ad) = if abs(kd) < T::epsilon() {
+            ([zero; 2], [one, zero])
+        } else {
+            if abs(gd) < T::epsilon() {
+                return Err("unlimited derivative gain");
+            }
+            let e = kd / gd;
+            ([kd, -kd], [one + e, one - e])
+        };
+        let a = polymul(ai, ad);
+        let b = polyadd(polyadd(polyscale(a, kp), polymul(bi, ad)), polymul(bd, ai));
+        if abs(ki) >= T::epsilon() && abs(b.iter().copied().sum::<T>()) < T::epsilon() {
+            return Err("low integrator gain and/or gain limit");
+        }
+        self.set_ba(b, a);
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for proportional-integral-double-integral
+    /// behavior with gain limit.
+    ///
+    /// The transfer function is `kp + ki*u + kii*u^2` with the gain limited
+    /// integrator `u = (1 + z^-1)/((1 + e) - (1 - e)*z^-1)`. `e` is chosen such that
+    /// the integral part `ki*u + kii*u^2` reaches the gain limit `g` at DC.
+    /// The integrator magnitude is `1/tan(pi*f)` well above the gain limit corner.
+    ///
+    /// # Arguments
+    ///
+    /// * `kp` - Proportional gain. Also defines gain sign.
+    /// * `ki` - Integral gain. Sign taken from `kp`.
+    /// * `kii` - Double integral gain. Sign taken from `kp`.
+    /// * `g` - Gain limit of the integral part. Zero for unlimited integrators.
+    pub fn set_pii(&mut self, kp: T, ki: T, kii: T, g: T) -> Result<(), &'static str> {
+        self.set_pii_signed(kp, copysign(ki, kp), copysign(kii, kp), copysign(g, kp))
+    }
+
+    /// Configures IIR filter coefficients for integral-double-integral behavior with gain
+    /// limit.
+    ///
+    /// This is `set_pii()` without proportional gain.
+    ///
+    /// # Arguments
+    ///
+    /// * `ki` - Integral gain. Sign taken from `kii`.
+    /// * `kii` - Double integral gain. Also defines gain sign.
+    /// * `g` - Gain limit of the integral part. Zero for unlimited integrators.
+    pub fn set_ii(&mut self, ki: T, kii: T, g: T) -> Result<(), &'static str> {
+        self.set_pii_signed(T::zero(), copysign(ki, kii), kii, copysign(g, kii))
+    }
+
+    fn set_pii_signed(&mut self, kp: T, ki: T, kii: T, g: T) -> Result<(), &'static str> {
+        let zero = T::zero();
+        let one = T::one();
+        let two = one + one;
+        let (aki, akii, ag) = (abs(ki), abs(kii), abs(g));
+        if akii < T::epsilon() && aki < T::epsilon() {
+            self.set_ba([kp, zero, zero], [one, zero, zero]);
+            return Ok(());
+        }
+        // Integrator DC gain v = 1/e
+        let e = if ag < T::epsilon() {
+            zero
+        } else if akii < T::epsilon() {
+            aki / ag
+        } else {
+            two * akii / ((aki * aki + two * two * akii * ag).sqrt() - aki)
+        };
+        let (bu, au) = ([one, one], [one + e, e - one]);
+        let (b, a) = if akii < T::epsilon() {
+            (
+                polyadd(
+                    polyscale([au[0], au[1], zero], kp),
+                    polyscale([one, one, zero], ki),
+                ),
+                [au[0], au[1], zero],
+            )
+        } else {
+            let a = polymul(au, au);
+            (
+                polyadd(
+                    polyadd(polyscale(a, kp), polyscale(polymul(bu, au), ki)),
+                    polyscale(polymul(bu, bu), kii),
+                ),
+                a,
+            )
+        };
+        if !e.is_finite() || abs(b.iter().copied().sum::<T>()) < T::epsilon() {
+            return Err("low integrator gain and/or gain limit");
+        }
+        self.set_ba(b, a);
+        Ok(())
+    }
+
+    /// Compute the filter parameter `alpha` for the biquad designers.
+    ///
+    /// `shelf` is the shelf amplitude `A = sqrt(g)`. `Shape::Slope` is only valid for
+    /// shelves.
+    ///
+    /// Returns `(cos(w0), alpha)` with `w0 = 2*pi*f`.
+    fn alpha(f: T, shape: Shape<T>, shelf: Option<T>) -> Result<(T, T), &'static str> {
+        let zero = T::zero();
+        let one = T::one();
+        let two: T = NumCast::from(2.0).unwrap();
+        if !(f > zero && f < one / two) {
+            return Err("frequency out of range");
+        }
+        let w0 = f * NumCast::from(2.0 * PI).unwrap();
+        let ln2: T = NumCast::from(LN_2).unwrap();
+        let (sin, cos) = w0.sin_cos();
+        let alpha = match shape {
+            Shape::Q(q) => sin / (two * q),
+            Shape::Bandwidth(bw) => sin * (ln2 / two * bw * w0 / sin).sinh(),
+            Shape::Slope(s) => {
+                let a = shelf.ok_or("slope only valid for shelves")?;
+                sin / two * ((a + one / a) * (one / s - one) + two).sqrt()
+            }
+        };
+        if !(alpha > zero && alpha.is_finite()) {
+            return Err("invalid shape");
+        }
+        Ok((cos, alpha))
+    }
+
+    /// Compute the amplitude `A = sqrt(g)` for the gain designers.
+    fn amplitude(g: T) -> Result<T, &'static str> {
+        if !(g > T::zero() && g.is_finite()) {
+            return Err("invalid gain");
+        }
+        Ok(g.sqrt())
+    }
+
+    /// Normalize and store biquad coefficients.
+    ///
+    /// # Arguments
+    /// * `b` - Feed-forward coefficients `[b0, b1, b2]`.
+    /// * `a` - Feed-back coefficients `[a0, a1, a2]`.
+    fn set_ba(&mut self, b: [T; 3], a: [T; 3]) {
+        let a0 = a[0];
+        self.ba = [b[0] / a0, b[1] / a0, b[2] / a0, -a[1] / a0, -a[2] / a0];
+    }
+
+    /// Configures IIR filter coefficients for second order lowpass behavior.
+    ///
+    /// The biquad designers here and below follow the Audio EQ Cookbook:
+    /// <https://webaudio.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html>.
+    /// They only modify `ba`, not `y_offset`, `y_min`, and `y_max`.
+    ///
+    /// # Arguments
+    /// * `f` - Corner frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Filter shape, typically `Shape::Q(1/sqrt(2))`.
+    /// * `k` - DC gain.
+    pub fn set_lowpass(&mut self, f: T, shape: Shape<T>, k: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        let b = k * (one - cos) / two;
+        self.set_ba([b, two * b, b], [one + alpha, -two * cos, one - alpha]);
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for second order highpass behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Corner frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Filter shape, typically `Shape::Q(1/sqrt(2))`.
+    /// * `k` - Gain at Nyquist.
+    pub fn set_highpass(&mut self, f: T, shape: Shape<T>, k: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        let b = k * (one + cos) / two;
+        self.set_ba([b, -two * b, b], [one + alpha, -two * cos, one - alpha]);
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for bandpass behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Center frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Filter bandwidth, `Shape::Q` or `Shape::Bandwidth`.
+    /// * `k` - Gain at the center frequency.
+    pub fn set_bandpass(&mut self, f: T, shape: Shape<T>, k: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        let b = k * alpha;
+        self.set_ba([b, T::zero(), -b], [one + alpha, -two * cos, one - alpha]);
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for notch behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Notch frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Notch width, `Shape::Q` or `Shape::Bandwidth`.
+    /// * `k` - Gain away from the notch (at DC and Nyquist).
+    pub fn set_notch(&mut self, f: T, shape: Shape<T>, k: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        self.set_ba(
+            [k, -two * cos * k, k],
+            [one + alpha, -two * cos, one - alpha],
+        );
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for allpass behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Frequency of 180 degree phase shift (in units of sample rate,
+    ///   `0 < f < 0.5`).
+    /// * `shape` - Width of the phase transition, `Shape::Q` or `Shape::Bandwidth`.
+    /// * `k` - Gain.
+    pub fn set_allpass(&mut self, f: T, shape: Shape<T>, k: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        self.set_ba(
+            [k * (one - alpha), -two * cos * k, k * (one + alpha)],
+            [one + alpha, -two * cos, one - alpha],
+        );
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for peaking (bell) equalizer behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Center frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Peak width, `Shape::Q` or `Shape::Bandwidth`.
+    /// * `g` - Gain at the center frequency. The gain at DC and Nyquist is 1.
+    pub fn set_peaking(&mut self, f: T, shape: Shape<T>, g: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let a = Self::amplitude(g)?;
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        self.set_ba(
+            [one + alpha * a, -two * cos, one - alpha * a],
+            [one + alpha / a, -two * cos, one - alpha / a],
+        );
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for low shelf behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Shelf midpoint frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Shelf transition, `Shape::Slope`, `Shape::Q`, or `Shape::Bandwidth`.
+    /// * `g` - Gain at DC. The gain at Nyquist is 1.
+    pub fn set_lowshelf(&mut self, f: T, shape: Shape<T>, g: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let a = Self::amplitude(g)?;
+        let (cos, alpha) = Self::alpha(f, shape, Some(a))?;
+        let s = two * a.sqrt() * alpha;
+        let (p, m) = (a + one, a - one);
+        self.set_ba(
+            [
+                a * (p - m * cos + s),
+                two * a * (m - p * cos),
+                a * (p - m * cos - s),
+            ],
+            [p + m * cos + s, -two * (m + p * cos), p + m * cos - s],
+        );
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for high shelf behavior.
+    ///
+    /// # Arguments
+    /// * `f` - Shelf midpoint frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Shelf transition, `Shape::Slope`, `Shape::Q`, or `Shape::Bandwidth`.
+    /// * `g` - Gain at Nyquist. The gain at DC is 1.
+    pub fn set_highshelf(&mut self, f: T, shape: Shape<T>, g: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        let a = Self::amplitude(g)?;
+        let (cos, alpha) = Self::alpha(f, shape, Some(a))?;
+        let s = two * a.sqrt() * alpha;
+        let (p, m) = (a + one, a - one);
+        self.set_ba(
+            [
+                a * (p + m * cos + s),
+                -two * a * (m + p * cos),
+                a * (p + m * cos - s),
+            ],
+            [p - m * cos + s, two * (m - p * cos), p - m * cos - s],
+        );
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for a notch with finite depth
+    /// or an anti-notch (resonance).
+    ///
+    /// The gain is `depth` at the center frequency and unity at DC and Nyquist.
+    /// Unlike `set_peaking()`, the width (of the poles) does not depend on the depth.
+    /// `depth = 0` is equivalent to `set_notch()`.
+    ///
+    /// # Arguments
+    /// * `f` - Center frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Width, `Shape::Q` or `Shape::Bandwidth`.
+    /// * `depth` - Gain at the center frequency. Notch for `depth < 1`, anti-notch
+    ///   for `depth > 1`.
+    pub fn set_notch_depth(&mut self, f: T, shape: Shape<T>, depth: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let two = one + one;
+        if !(depth >= T::zero() && depth.is_finite()) {
+            return Err("invalid depth");
+        }
+        let (cos, alpha) = Self::alpha(f, shape, None)?;
+        self.set_ba(
+            [one + alpha * depth, -two * cos, one - alpha * depth],
+            [one + alpha, -two * cos, one - alpha],
+        );
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients for first order phase lead or lag
+    /// compensation.
+    ///
+    /// The maximum phase shift `phase` is reached at the center frequency `f`.
+    /// The filter is the bilinear transform (prewarped at `f`) of
+    /// `k*sqrt(1/r)*(1 + s/wz)/(1 + s/wp)` with `wp = r*wz` and `sqrt(wz*wp) = 2*pi*f`.
+    /// The ratio is `r = (1 + sin(phase))/(1 - sin(phase))`.
+    ///
+    /// # Arguments
+    /// * `f` - Center frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `phase` - Phase boost in radians, `-pi/2 < phase < pi/2`.
+    ///   Lead for positive, lag for negative phase.
+    /// * `k` - Gain at the center frequency.
+    pub fn set_lead_lag(&mut self, f: T, phase: T, k: T) -> Result<(), &'static str> {
+        let one = T::one();
+        let pi: T = NumCast::from(PI).unwrap();
+        let half_pi = pi / (one + one);
+        if phase.is_nan() || phase.abs() >= half_pi {
+            return Err("phase out of range");
+        }
+        let sin = phase.sin();
+        let r = (one + sin) / (one - sin);
+        let w0 = (one + one) * pi * f;
+        let (wz, wp) = (w0 / r.sqrt(), w0 * r.sqrt());
+        let g = k / r.sqrt();
+        self.set_analog(
+            [T::zero(), g / wz, g],
+            [T::zero(), one / wp, one],
+            one,
+            Transform::Prewarp(f),
+        )
+    }
+
     /// Compute the overall (DC feed-forward) gain.
     pub fn get_k(&self) -> T {
         self.ba[..3].iter().copied().sum()
@@ -129,13 +551,122 @@ impl<T: Float + Default + Sum<T>> IIR<T> {
         self.y_offset = xo * self.get_k();
     }
 
+    /// Ramp the output (`y`) offset toward a target with limited rate and acceleration.
+    ///
+    /// Call once per sample before `update()`. See `Slew`.
+    ///
+    /// # Arguments
+    /// * `slew` - Rate and acceleration limits.
+    /// * `v` - Current offset rate (slew limiter state).
+    /// * `y_offset` - Target output offset.
+    pub fn slew_y_offset(&mut self, slew: &Slew<T>, v: &mut T, y_offset: T)
+    where
+        T: SlewSample,
+    {
+        let mut yv = [self.y_offset, *v];
+        self.y_offset = slew.update(&mut yv, y_offset);
+        *v = yv[1];
+    }
+
+    /// Compute a steady state for a given output.
+    ///
+    /// The returned state is consistent with constant input and output
+    /// (`[x, x, y, y, y]`) where the input `x` is chosen such that the filter
+    /// keeps producing the output `y`. Loading it into the state before
+    /// switching to new coefficients gives bumpless transfer.
+    /// For integrating filters, `x` is the input that leaves the output unchanged.
+    ///
+    /// # Arguments
+    /// * `y` - Desired output, within `y_min` and `y_max`.
+    pub fn state_for_output(&self, y: T) -> Result<Vec5<T>, &'static str> {
+        if y < self.y_min || y > self.y_max {
+            return Err("output out of limits");
+        }
+        let k = self.get_k();
+        if abs(k) < T::epsilon() {
+            return Err("k is zero");
+        }
+        let x = (y * (T::one() - self.ba[3] - self.ba[4]) - self.y_offset) / k;
+        Ok([x, x, y, y, y])
+    }
+
+    /// Compute the steady state for a given input.
+    ///
+    /// The returned state (`[x, x, y, y, y]`) contains the (limited) output `y`
+    /// the filter settles to for constant input `x`.
+    ///
+    /// # Arguments
+    /// * `x` - Input.
+    pub fn state_for_input(&self, x: T) -> Result<Vec5<T>, &'static str> {
+        let d = T::one() - self.ba[3] - self.ba[4];
+        if abs(d) < T::epsilon() {
+            return Err("infinite DC gain");
+        }
+        let y = ((self.y_offset + self.get_k() * x) / d)
+            .max(self.y_min)
+            .min(self.y_max);
+        Ok([x, x, y, y, y])
+    }
+
+    /// Compute the poles of the transfer function.
+    pub fn poles(&self) -> [Complex<T>; 2] {
+        roots([T::one(), -self.ba[3], -self.ba[4]])
+    }
+
+    /// Compute the zeros of the transfer function.
+    ///
+    /// Zeros at infinity (vanishing `b0`, `b1`) are returned as infinite.
+    pub fn zeros(&self) -> [Complex<T>; 2] {
+        roots([self.ba[0], self.ba[1], self.ba[2]])
+    }
+
+    /// Check the filter configuration.
+    ///
+    /// Poles on the unit circle (e.g. integrators, which rely on the limits
+    /// for anti-windup) are accepted.
+    ///
+    /// Errors, in order of precedence:
+    /// * `Invalid::Range` if any coefficient is not finite or outside the
+    ///   integer Q2.30 range (`|c| < 2`, see `iir_int::IIR`).
+    /// * `Invalid::Unstable` if a pole is outside the unit circle.
+    /// * `Invalid::Limits` if `y_min > y_max` or a limit is NaN.
+    pub fn validate(&self) -> Result<(), Invalid> {
+        let two = T::one() + T::one();
+        if !self.ba.iter().all(|c| c.is_finite() && abs(*c) < two) {
+            return Err(Invalid::Range);
+        }
+        // Jury criterion for `z**2 + a1*z + a2`: `|a2| <= 1` and `|a1| <= 1 + a2`
+        if abs(self.ba[4]) > T::one() || abs(self.ba[3]) > T::one() - self.ba[4] {
+            return Err(Invalid::Unstable);
+        }
+        if self.y_min > self.y_max || self.y_min.is_nan() || self.y_max.is_nan() {
+            return Err(Invalid::Limits);
+        }
+        Ok(())
+    }
+
     /// Feed a new input value into the filter, update the filter state, and
     /// return the new output. Only the state `xy` is modified.
     ///
     /// # Arguments
     /// * `xy` - Current filter state.
     /// * `x0` - New input.
+    /// * `hold` - Hold the output: repeat the previous output instead of computing
+    ///   a new one. This freezes integrators.
     pub fn update(&self, xy: &mut Vec5<T>, x0: T, hold: bool) -> T {
+        self.update_clip(xy, x0, hold).0
+    }
+
+    /// Like `update()` but also report whether the output was limited.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the output, see `update()`.
+    ///
+    /// # Returns
+    /// The new output and the limit that was hit, if any.
+    pub fn update_clip(&self, xy: &mut Vec5<T>, x0: T, hold: bool) -> (T, Option<Clip>) {
         let n = self.ba.len();
         debug_assert!(xy.len() == n);
         // `xy` contains       x0 x1 y0 y1 y2
@@ -152,9 +683,676 @@ impl<T: Float + Default + Sum<T>> IIR<T> {
             macc(self.y_offset, xy, &self.ba)
         };
         // Limit y0
-        let y0 = clamp(y0, self.y_min, self.y_max);
+        let y = y0.max(self.y_min).min(self.y_max);
+        let clip = if y0 > y {
+            Some(Clip::Max)
+        } else if y0 < y {
+            Some(Clip::Min)
+        } else {
+            None
+        };
+        let y0 = y;
         // Store y0            x0 x1 y0 y1 y2
         xy[n / 2] = y0;
+        (y0, clip)
+    }
+
+    /// Filter a block of samples in place.
+    ///
+    /// Equivalent to calling `update()` for each sample.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x` - Input samples, replaced by the output samples.
+    /// * `hold` - Hold the output, see `update()`.
+    pub fn update_block(&self, xy: &mut Vec5<T>, x: &mut [T], hold: bool) {
+        for x in x.iter_mut() {
+            *x = self.update(xy, *x, hold);
+        }
+    }
+
+    /// Filter a block of samples.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x` - Input samples.
+    /// * `y` - Output samples. Must be the same length as `x`.
+    /// * `hold` - Hold the output, see `update()`.
+    pub fn update_block_to(&self, xy: &mut Vec5<T>, x: &[T], y: &mut [T], hold: bool) {
+        debug_assert_eq!(x.len(), y.len());
+        for (x, y) in x.iter().zip(y.iter_mut()) {
+            *y = self.update(xy, *x, hold);
+        }
+    }
+
+    /// Filter a block of channel-interleaved samples in place.
+    ///
+    /// The `C` channels share the coefficients, offset, and limits but have
+    /// independent states. The coefficients are held in locals and the inner loop
+    /// runs over the channels with the states transposed (channels contiguous)
+    /// such that the channels can be vectorized.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter states, one per channel.
+    /// * `x` - Interleaved input samples (`[x0[0], x0[1], .., x0[C - 1], x1[0], ..]`),
+    ///   replaced by the output samples. The length must be a multiple of `C`.
+    /// * `hold` - Hold the output, see `update()`.
+    pub fn update_interleaved<const C: usize>(
+        &self,
+        xy: &mut [Vec5<T>; C],
+        x: &mut [T],
+        hold: bool,
+    ) {
+        debug_assert_eq!(x.len() % C, 0);
+        // Coefficients, offset, and limits in locals
+        let [b0, b1, b2, a1, a2] = self.ba;
+        let (y_offset, y_min, y_max) = (self.y_offset, self.y_min, self.y_max);
+        // Transpose the states such that the channels are contiguous for each element
+        let mut s = transpose(xy);
+        let [x1, x2, y1, y2, y3] = &mut s;
+        for x in x.chunks_exact_mut(C) {
+            for (c, x) in x.iter_mut().enumerate() {
+                // See `update_clip()`
+                let x0 = *x;
+                let y0 = if hold {
+                    y1[c]
+                } else {
+                    y_offset + b0 * x0 + b1 * x1[c] + b2 * x2[c] + a1 * y1[c] + a2 * y2[c]
+                };
+                let y0 = y0.max(y_min).min(y_max);
+                x2[c] = x1[c];
+                x1[c] = x0;
+                y3[c] = y2[c];
+                y2[c] = y1[c];
+                y1[c] = y0;
+                *x = y0;
+            }
+        }
+        *xy = transpose(&s);
+    }
+
+    /// Track an externally forced output.
+    ///
+    /// Instead of computing the output, the filter takes the forced output `y0`
+    /// (limited) and records it in the state together with the new input.
+    /// Once released (calling `update()` again) it continues bumplessly from
+    /// the forced output.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `y0` - Forced output.
+    pub fn track(&self, xy: &mut Vec5<T>, x0: T, y0: T) -> T {
+        // See `update()`
+        xy.copy_within(0..4, 1);
+        xy[0] = x0;
+        let y0 = y0.max(self.y_min).min(self.y_max);
+        xy[2] = y0;
+        y0
+    }
+}
+
+/// Cascade of `N` biquad IIR sections with common offset and limits.
+///
+/// The input is fed into section 0, its output into section 1 and so on.
+/// Each section has its own coefficients (`ba[i]`) and state.
+/// The output offset `y_offset` and the output limits `y_min` and `y_max`
+/// act on the last section like they do for `IIR`. The other sections are
+/// neither offset nor limited.
+///
+/// The inherent anti-windup of `IIR` only applies to the last section.
+/// Integrating sections should therefore be placed last.
+///
+/// # Miniconf
+///
+/// `{"y_offset": y_offset, "y_min": y_min, "y_max": y_max, "ba": [[b0, b1, b2, a1, a2], ...]}`
+///
+/// See `IIR` for the individual items. The coefficients are accessible per section
+/// and element (e.g. `ba/1/3` is `a1` of the second section).
+#[derive(Copy, Clone, Debug, Miniconf)]
+pub struct Cascade<T, const N: usize> {
+    pub ba: [Vec5<T>; N],
+    pub y_offset: T,
+    pub y_min: T,
+    pub y_max: T,
+}
+
+impl<T: Default + Copy, const N: usize> Default for Cascade<T, N> {
+    fn default() -> Self {
+        Self {
+            ba: [Vec5::default(); N],
+            y_offset: T::default(),
+            y_min: T::default(),
+            y_max: T::default(),
+        }
+    }
+}
+
+impl<T: Float + Default + Sum<T>, const N: usize> Cascade<T, N> {
+    /// Create a new cascade with the given overall gain.
+    ///
+    /// The first section is a gain, all other sections are identity.
+    pub fn new(gain: T, y_min: T, y_max: T) -> Self {
+        let mut ba = [[T::zero(); 5]; N];
+        for ba in ba.iter_mut() {
+            ba[0] = T::one();
+        }
+        ba[0][0] = gain;
+        Self {
+            ba,
+            y_offset: T::zero(),
+            y_min,
+            y_max,
+        }
+    }
+
+    /// Compute the overall feed-forward gain relating input offset and `y_offset`.
+    ///
+    /// This is the product of the DC gains of the sections before the last
+    /// and the DC feed-forward gain of the last section.
+    pub fn get_k(&self) -> T {
+        self.ba.iter().enumerate().fold(T::one(), |k, (i, ba)| {
+            let b: T = ba[..3].iter().copied().sum();
+            if i == N - 1 {
+                k * b
+            } else {
+                k * b / (T::one() - ba[3] - ba[4])
+            }
+        })
+    }
+
+    /// Compute input-referred (`x`) offset from output (`y`) offset.
+    pub fn get_x_offset(&self) -> Result<T, &str> {
+        let k = self.get_k();
+        if abs(k) < T::epsilon() {
+            Err("k is zero")
+        } else {
+            Ok(self.y_offset / k)
+        }
+    }
+
+    /// Convert input (`x`) offset to equivalent output (`y`) offset and apply.
+    ///
+    /// # Arguments
+    /// * `xo`: Input (`x`) offset.
+    pub fn set_x_offset(&mut self, xo: T) {
+        self.y_offset = xo * self.get_k();
+    }
+
+    /// Feed a new input value into the filter cascade, update the filter states, and
+    /// return the new output. Only the states `xy` are modified.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter states, one per section.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the outputs of all sections.
+    pub fn update(&self, xy: &mut [Vec5<T>; N], x0: T, hold: bool) -> T {
+        let mut y0 = x0;
+        for (i, (ba, xy)) in self.ba.iter().zip(xy.iter_mut()).enumerate() {
+            // See `IIR::update()`
+            xy.copy_within(0..4, 1);
+            xy[0] = y0;
+            y0 = if hold {
+                xy[3]
+            } else if i == N - 1 {
+                macc(self.y_offset, xy, ba).max(self.y_min).min(self.y_max)
+            } else {
+                macc(T::zero(), xy, ba)
+            };
+            xy[2] = y0;
+        }
         y0
     }
 }
+
+/// Output limit hit in `update_clip()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Clip {
+    /// The output was limited to `y_min`.
+    Min,
+    /// The output was limited to `y_max`.
+    Max,
+}
+
+/// Reasons for rejecting a filter configuration in `validate()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Invalid {
+    /// A coefficient is not finite or out of range.
+    Range,
+    /// A pole is outside the unit circle.
+    Unstable,
+    /// The output limits are inverted (`y_min > y_max`) or invalid.
+    Limits,
+}
+
+impl Invalid {
+    /// Short description of the error.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Range => "coefficient out of range",
+            Self::Unstable => "unstable",
+            Self::Limits => "invalid limits",
+        }
+    }
+}
+
+/// Transpose a `M x N` array.
+pub(crate) fn transpose<T: Copy + Default, const M: usize, const N: usize>(
+    a: &[[T; N]; M],
+) -> [[T; M]; N] {
+    let mut t = [[T::default(); M]; N];
+    for (i, a) in a.iter().enumerate() {
+        for (t, a) in t.iter_mut().zip(a.iter()) {
+            t[i] = *a;
+        }
+    }
+    t
+}
+
+/// Roots of the quadratic polynomial `c[0]*z**2 + c[1]*z + c[2]`.
+///
+/// Missing roots (vanishing leading coefficients) are infinite.
+fn roots<T: Float + Default>(c: [T; 3]) -> [Complex<T>; 2] {
+    let two = T::one() + T::one();
+    let inf = Complex::new(T::infinity(), T::zero());
+    if c[0] == T::zero() {
+        return [Complex::new(-c[2] / c[1], T::zero()), inf];
+    }
+    let d = c[1] * c[1] - two * two * c[0] * c[2];
+    if d < T::zero() {
+        let re = -c[1] / (two * c[0]);
+        let im = (-d).sqrt() / (two * c[0]);
+        [Complex::new(re, im), Complex::new(re, -im)]
+    } else {
+        // Avoid cancellation
+        let q = -(c[1] + copysign(d.sqrt(), c[1])) / two;
+        if q == T::zero() {
+            [Complex::new(T::zero(), T::zero()); 2]
+        } else {
+            [
+                Complex::new(q / c[0], T::zero()),
+                Complex::new(c[2] / q, T::zero()),
+            ]
+        }
+    }
+}
+
+/// Multiply two first order polynomials.
+fn polymul<T: Float>(a: [T; 2], b: [T; 2]) -> [T; 3] {
+    [a[0] * b[0], a[0] * b[1] + a[1] * b[0], a[1] * b[1]]
+}
+
+fn polyadd<T: Float>(a: [T; 3], b: [T; 3]) -> [T; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn polyscale<T: Float>(a: [T; 3], k: T) -> [T; 3] {
+    [a[0] * k, a[1] * k, a[2] * k]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::isclose;
+    use core::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    #[test]
+    fn cookbook() {
+        let mut i = IIR::<f64>::default();
+        let q = Shape::Q(FRAC_1_SQRT_2);
+        let f = 0.1;
+
+        i.set_lowpass(f, q, 2.).unwrap();
+        assert!(isclose(i.response(0.).norm(), 2., 1e-12, 0.));
+        assert!(isclose(i.response(f).norm(), 2. * FRAC_1_SQRT_2, 1e-12, 0.));
+        assert!(i.response(0.5).norm() < 1e-12);
+
+        i.set_highpass(f, q, 2.).unwrap();
+        assert!(i.response(0.).norm() < 1e-12);
+        assert!(isclose(i.response(f).norm(), 2. * FRAC_1_SQRT_2, 1e-12, 0.));
+        assert!(isclose(i.response(0.5).norm(), 2., 1e-12, 0.));
+
+        i.set_bandpass(f, Shape::Bandwidth(1.), 3.).unwrap();
+        assert!(i.response(0.).norm() < 1e-12);
+        assert!(isclose(i.response(f).norm(), 3., 1e-12, 0.));
+
+        i.set_notch(f, Shape::Q(2.), 3.).unwrap();
+        assert!(isclose(i.response(0.).norm(), 3., 1e-12, 0.));
+        assert!(i.response(f).norm() < 1e-12);
+
+        i.set_allpass(f, Shape::Q(2.), 3.).unwrap();
+        for f in [0., 0.01, 0.1, 0.3, 0.5].iter() {
+            assert!(isclose(i.response(*f).norm(), 3., 1e-12, 0.));
+        }
+
+        i.set_peaking(f, Shape::Q(2.), 4.).unwrap();
+        assert!(isclose(i.response(0.).norm(), 1., 1e-12, 0.));
+        assert!(isclose(i.response(f).norm(), 4., 1e-12, 0.));
+        assert!(isclose(i.response(0.5).norm(), 1., 1e-12, 0.));
+
+        i.set_lowshelf(f, Shape::Slope(1.), 4.).unwrap();
+        assert!(isclose(i.response(0.).norm(), 4., 1e-12, 0.));
+        assert!(isclose(i.response(f).norm(), 2., 1e-12, 0.));
+        assert!(isclose(i.response(0.5).norm(), 1., 1e-12, 0.));
+
+        i.set_highshelf(f, Shape::Slope(1.), 4.).unwrap();
+        assert!(isclose(i.response(0.).norm(), 1., 1e-12, 0.));
+        assert!(isclose(i.response(f).norm(), 2., 1e-12, 0.));
+        assert!(isclose(i.response(0.5).norm(), 4., 1e-12, 0.));
+    }
+
+    #[test]
+    fn pid() {
+        let mut i = IIR::<f64>::default();
+        let mut j = IIR::<f64>::default();
+        for (kp, ki, g) in [(1., 0.1, 10.), (-1., 1e-3, 0.), (0.5, 0., 1.)].iter() {
+            i.set_pi(*kp, *ki, *g).unwrap();
+            j.set_pid(*kp, *ki, 0., *g, 0.).unwrap();
+            for (i, j) in i.ba.iter().zip(j.ba.iter()) {
+                assert!(isclose(*i, *j, 1e-12, 1e-15));
+            }
+            j.set_pii(*kp, *ki, 0., *g).unwrap();
+            for (i, j) in i.ba.iter().zip(j.ba.iter()) {
+                assert!(isclose(*i, *j, 1e-12, 1e-15));
+            }
+        }
+
+        i.set_pid(2., 1e-2, 10., 1e3, 20.).unwrap();
+        assert!(isclose(i.response(0.).norm(), 2. + 1e3, 1e-9, 0.));
+        assert!(isclose(i.response(0.5).norm(), 2. + 20., 1e-12, 0.));
+        // Derivative action dominates at intermediate frequencies
+        let f = 1e-2;
+        let d = 10. * (PI * f).tan();
+        assert!(isclose(i.response(f).norm(), (4. + d * d).sqrt(), 2e-2, 0.));
+
+        // Negative sign
+        i.set_pid(-2., 1e-2, 10., 1e3, 20.).unwrap();
+        assert!(isclose(
+            i.get_k() / (1. - i.ba[3] - i.ba[4]),
+            -2. - 1e3,
+            1e-9,
+            0.
+        ));
+
+        assert!(i.set_pid(1., 1e-2, 1., 1e3, 0.).is_err());
+    }
+
+    #[test]
+    fn pii() {
+        let mut i = IIR::<f64>::default();
+        i.set_pii(1., 1e-2, 1e-4, 1e3).unwrap();
+        assert!(isclose(
+            i.get_k() / (1. - i.ba[3] - i.ba[4]),
+            1. + 1e3,
+            1e-9,
+            0.
+        ));
+        assert!(isclose(i.response(0.5).norm(), 1., 1e-12, 1e-12));
+        // Integrator with gain limit: u = 1/(e + j*tan(pi*f)), 1e-2/e + 1e-4/e^2 = 1e3
+        let e = 2e-4 / ((1e-4f64 + 4e-4 * 1e3).sqrt() - 1e-2);
+        for f in [1e-5, 1e-3, 1e-1].iter() {
+            let u = Complex::new(e, (PI * f).tan()).inv();
+            let h = u * u * 1e-4 + u * 1e-2 + 1.;
+            assert!(isclose(
+                i.response(*f).norm(),
+                h.norm_sqr().sqrt(),
+                1e-9,
+                0.
+            ));
+        }
+
+        i.set_ii(1e-2, -1e-4, 0.).unwrap();
+        assert_eq!(&i.ba[3..], &[2., -1.]);
+        assert!(isclose(i.get_k(), -4e-4, 1e-12, 0.));
+    }
+
+    #[test]
+    fn cascade() {
+        let mut c = Cascade::<f64, 3>::new(1., -1., 1.);
+        c.set_x_offset(0.1);
+        assert_eq!(c.y_offset, 0.1);
+        let mut i = [IIR::<f64>::new(1., -1e9, 1e9); 3];
+        i[0].set_lowpass(0.1, Shape::Q(2.), 2.).unwrap();
+        i[1].set_pi(1., 0.1, 10.).unwrap();
+        i[2].set_highpass(0.01, Shape::Q(0.5), 1.).unwrap();
+        for (c, i) in c.ba.iter_mut().zip(i.iter()) {
+            *c = i.ba;
+        }
+        c.set("ba/2/0", b"0.5").unwrap();
+        i[2].ba[0] = 0.5;
+        i[2].y_offset = 0.3;
+        i[2].y_min = -0.1;
+        i[2].y_max = 0.2;
+        c.set("y_offset", b"0.3").unwrap();
+        c.set("y_min", b"-0.1").unwrap();
+        c.set("y_max", b"0.2").unwrap();
+        let mut xy = [[0.; 5]; 3];
+        let mut xyi = [[0.; 5]; 3];
+        for n in 0..1000 {
+            let x = ((n * 7) % 11) as f64 * 0.05 - 0.2;
+            let hold = n % 17 == 0;
+            let y = c.update(&mut xy, x, hold);
+            let yi = i
+                .iter()
+                .zip(xyi.iter_mut())
+                .fold(x, |x, (i, xy)| i.update(xy, x, hold));
+            assert_eq!(y, yi);
+        }
+        assert_eq!(xy, xyi);
+    }
+
+    #[test]
+    fn poles_zeros() {
+        let mut i = IIR::<f64>::default();
+        i.set_lowpass(0.1, Shape::Q(2.), 1.).unwrap();
+        // Reconstruct the denominator from the poles
+        let p = i.poles();
+        assert!(isclose(p[0].im, -p[1].im, 0., 1e-12));
+        assert!(isclose(-(p[0] + p[1]).re, -i.ba[3], 1e-12, 0.));
+        assert!(isclose((p[0] * p[1]).re, -i.ba[4], 1e-12, 0.));
+        assert!(p[0].norm() < 1.);
+        for z in i.zeros().iter() {
+            assert!(isclose(z.re, -1., 0., 1e-6));
+            assert!(isclose(z.im, 0., 0., 1e-6));
+        }
+        // Real roots
+        i.ba = [0.5, -0.75, 0.25, 1.5, -0.5];
+        let mut p = i.poles().map(|p| p.re);
+        p.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(p, [0.5, 1.]);
+        let mut z = i.zeros().map(|z| z.re);
+        z.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(z, [0.5, 1.]);
+        // Pure delay
+        i.ba = [0., 1., 0., 0., 0.];
+        assert_eq!(i.zeros()[0].re, 0.);
+        assert!(i.zeros()[1].re.is_infinite());
+        assert_eq!(i.poles(), [Complex::new(0., 0.); 2]);
+    }
+
+    #[test]
+    fn validate() {
+        let mut i = IIR::<f64>::new(1., -1., 1.);
+        assert_eq!(i.validate(), Ok(()));
+        i.set_pi(1.5, 1e-3, f64::INFINITY).unwrap();
+        assert_eq!(i.validate(), Ok(()));
+        // Outside Q2.30
+        i.set_pi(2., 1e-3, f64::INFINITY).unwrap();
+        assert_eq!(i.validate(), Err(Invalid::Range));
+        i.set_ii(1e-3, 1e-4, 1e6).unwrap();
+        assert_eq!(i.validate(), Ok(()));
+        // Unlimited double integrator: `a1 = 2` is outside Q2.30
+        i.set_ii(1e-3, 1e-4, f64::INFINITY).unwrap();
+        assert_eq!(i.validate(), Err(Invalid::Range));
+        i.set_lowpass(0.01, Shape::Q(10.), 1.).unwrap();
+        assert_eq!(i.validate(), Ok(()));
+        i.ba[4] = -1.001;
+        assert_eq!(i.validate(), Err(Invalid::Unstable));
+        i.ba = [1., 0., 0., 1.001, 0.];
+        assert_eq!(i.validate(), Err(Invalid::Unstable));
+        i.ba = [1., 0., 0., -1.5, -0.4];
+        assert_eq!(i.validate(), Err(Invalid::Unstable));
+        i.ba = [f64::NAN, 0., 0., 0., 0.];
+        assert_eq!(i.validate(), Err(Invalid::Range));
+        let i = IIR::<f64>::new(5., -1., 1.);
+        assert_eq!(i.validate(), Err(Invalid::Range));
+        let mut i = IIR::<f64>::new(1., -1., 1.);
+        i.ba = [1., 0., 0., 0., 0.];
+        i.y_min = 2.;
+        assert_eq!(i.validate(), Err(Invalid::Limits));
+        i.y_min = f64::NAN;
+        assert_eq!(i.validate(), Err(Invalid::Limits));
+    }
+
+    #[test]
+    fn bumpless() {
+        let mut i = IIR::<f64>::new(1., -10., 10.);
+        i.set_lowpass(0.01, Shape::Q(2.), 3.).unwrap();
+        i.y_offset = 0.5;
+        let mut xy = i.state_for_output(2.).unwrap();
+        let x = xy[0];
+        for _ in 0..10 {
+            assert!(isclose(i.update(&mut xy, x, false), 2., 1e-12, 0.));
+        }
+        i.y_offset = 0.;
+        let mut xy = i.state_for_input(1.).unwrap();
+        assert!(isclose(xy[2], 3., 1e-12, 0.));
+        for _ in 0..10 {
+            assert!(isclose(i.update(&mut xy, 1., false), 3., 1e-12, 0.));
+        }
+        assert!(i.state_for_output(11.).is_err());
+
+        // Switch from hold to a PI controller
+        i.set_pi(-0.5, -1e-3, f64::INFINITY).unwrap();
+        i.y_offset = 1.;
+        assert!(i.state_for_input(0.).is_err());
+        let mut xy = i.state_for_output(4.).unwrap();
+        assert!(isclose(xy[0], -1. / i.get_k(), 1e-12, 0.));
+        let x = xy[0];
+        assert!(isclose(i.update(&mut xy, x, false), 4., 1e-12, 0.));
+
+        // Track a forced output, then release
+        let mut xy = [0.; 5];
+        for _ in 0..10 {
+            assert_eq!(i.track(&mut xy, 0., 5.), 5.);
+        }
+        assert_eq!(i.track(&mut xy, 0., 20.), 10.);
+        assert_eq!(i.track(&mut xy, 0., 5.), 5.);
+        i.y_offset = 0.;
+        assert_eq!(i.update(&mut xy, 0., false), 5.);
+        let y = i.update(&mut xy, 1., false);
+        assert!(isclose(y, 5. + i.ba[0], 1e-12, 0.));
+    }
+
+    #[test]
+    fn block() {
+        let mut i = IIR::<f32>::new(1., -2., 2.);
+        i.set_lowpass(0.1, Shape::Q(1.), 1.5).unwrap();
+        let x: Vec<f32> = (0..64).map(|n| ((n * 7) % 11) as f32 / 5. - 1.).collect();
+        let mut xy0 = [0.; 5];
+        let y0: Vec<f32> = x.iter().map(|x| i.update(&mut xy0, *x, false)).collect();
+
+        let mut xy = [0.; 5];
+        let mut y = x.clone();
+        for y in y.chunks_mut(24) {
+            i.update_block(&mut xy, y, false);
+        }
+        assert_eq!(y, y0);
+        assert_eq!(xy, xy0);
+
+        let mut xy = [0.; 5];
+        let mut y = vec![0.; x.len()];
+        i.update_block_to(&mut xy, &x, &mut y, false);
+        assert_eq!(y, y0);
+
+        let mut xy = [[0.; 5]; 3];
+        let mut y: Vec<f32> = x.iter().flat_map(|x| [*x, 0., -*x]).collect();
+        i.update_interleaved(&mut xy, &mut y, false);
+        for (y, y0) in y.chunks_exact(3).zip(y0.iter()) {
+            assert_eq!(y[0], *y0);
+            assert_eq!(y[1], 0.);
+            assert_eq!(y[2], -*y0);
+        }
+        assert_eq!(xy[0], xy0);
+        i.update_interleaved(&mut xy, &mut y[..6], true);
+        assert_eq!(y[0], xy0[2]);
+    }
+
+    #[test]
+    fn clip() {
+        let mut i = IIR::<f64>::new(1., -1., 1.);
+        // Integrator
+        i.ba = [0.5, 0., 0., 1., 0.];
+        let mut xy = [0.; 5];
+        assert_eq!(i.update_clip(&mut xy, 1., false), (0.5, None));
+        assert_eq!(i.update_clip(&mut xy, 1., false), (1., None));
+        assert_eq!(i.update_clip(&mut xy, 1., false), (1., Some(Clip::Max)));
+        // Frozen integrator
+        assert_eq!(i.update_clip(&mut xy, -1., true), (1., None));
+        assert_eq!(i.update_clip(&mut xy, -1., false), (0.5, None));
+        assert_eq!(i.update_clip(&mut xy, -8., false), (-1., Some(Clip::Min)));
+        // Inverted limits are consistent between all paths
+        let i = IIR::<f64>::new(1., 10., -10.);
+        assert_eq!(
+            i.update_clip(&mut [0.; 5], 0., false),
+            (-10., Some(Clip::Max))
+        );
+        let mut y = [0.; 2];
+        i.update_interleaved(&mut [[0.; 5]; 2], &mut y, false);
+        assert_eq!(y, [-10.; 2]);
+        assert_eq!(i.track(&mut [0.; 5], 0., 0.), -10.);
+        assert_eq!(i.state_for_input(0.).unwrap()[2], -10.);
+    }
+
+    #[test]
+    fn compensators() {
+        let mut i = IIR::<f64>::default();
+        let f = 0.02;
+        for depth in [0., 0.1, 1., 10.].iter() {
+            i.set_notch_depth(f, Shape::Q(5.), *depth).unwrap();
+            assert!(isclose(i.response(f).norm(), *depth, 0., 1e-12));
+            assert!(isclose(i.response(0.).norm(), 1., 1e-12, 0.));
+            assert!(isclose(i.response(0.5).norm(), 1., 1e-12, 0.));
+            // Pole width independent of depth
+            assert!(isclose(i.poles()[0].norm(), (-i.ba[4]).sqrt(), 1e-12, 0.));
+        }
+        let poles = i.poles();
+        i.set_notch_depth(f, Shape::Q(5.), 0.3).unwrap();
+        assert_eq!(i.poles(), poles);
+        assert!(i.set_notch_depth(f, Shape::Q(5.), -1.).is_err());
+
+        for phase in [-1.2, -0.3, 0., 0.5, 1.4].iter() {
+            i.set_lead_lag(f, *phase, 2.).unwrap();
+            let h = i.response(f);
+            assert!(isclose(h.norm(), 2., 1e-9, 0.));
+            assert!(isclose(h.arg(), *phase, 0., 1e-9));
+            // Maximum phase shift
+            for df in [0.9, 1.1].iter() {
+                assert!(i.response(f * df).arg().abs() <= phase.abs() + 1e-12);
+            }
+            // First order
+            assert_eq!((i.ba[2], i.ba[4]), (0., 0.));
+        }
+        assert!(i.set_lead_lag(f, 1.6, 1.).is_err());
+        assert!(i.set_lead_lag(0.6, 0.5, 1.).is_err());
+    }
+
+    #[test]
+    fn cookbook_invalid() {
+        let mut i = IIR::new(1f32, -1., 1.);
+        assert!(i.set_lowpass(0.5, Shape::Q(1.), 1.).is_err());
+        assert!(i.set_lowpass(0., Shape::Q(1.), 1.).is_err());
+        assert!(i.set_notch(0.1, Shape::Q(0.), 1.).is_err());
+        assert!(i.set_bandpass(0.1, Shape::Bandwidth(-1.), 1.).is_err());
+        assert!(i.set_lowpass(0.1, Shape::Slope(1.), 1.).is_err());
+        assert!(i.set_peaking(0.1, Shape::Slope(1.), 2.).is_err());
+        for g in [0., -1., f32::INFINITY, f32::NAN].iter() {
+            assert!(i.set_peaking(0.1, Shape::Q(1.), *g).is_err());
+            assert!(i.set_lowshelf(0.1, Shape::Q(1.), *g).is_err());
+            assert!(i.set_highshelf(0.1, Shape::Slope(1.), *g).is_err());
+        }
+        assert_eq!(i.ba, [1., 0., 0., 0., 0.]);
+        i.set_lowpass(0.1, Shape::Q(1.), 1.).unwrap();
+        assert_eq!((i.y_offset, i.y_min, i.y_max), (0., -1., 1.));
+    }
+}
@@ -0,0 +1,245 @@
+use super::{polyadd, polymul, polyscale, roots, IIR};
+use crate::Complex;
+use core::f64::consts::PI;
+use core::iter::Sum;
+use num_traits::{Float, NumCast};
+use serde::{Deserialize, Serialize};
+
+/// Continuous-time to discrete-time transform
+///
+/// Frequencies are in the same units as the sample rate (e.g. Hz).
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Transform<T> {
+    /// Bilinear (Tustin) transform `s = 2*fs*(1 - z^-1)/(1 + z^-1)`
+    Tustin,
+    /// Bilinear transform with frequency prewarping.
+    /// The response at the given frequency (`0 < f < fs/2`) is preserved.
+    Prewarp(T),
+    /// Matched-Z transform: poles and finite zeros are mapped using `z = exp(s/fs)`.
+    /// Zeros at infinity are dropped. The gain magnitude is matched at the given
+    /// frequency (`0 <= f < fs/2`, typically DC).
+    MatchedZ(T),
+}
+
+/// Polynomial `c[0]*s**2 + c[1]*s + c[2]` from up to two roots.
+fn poly<T: Float>(r: &[Complex<T>]) -> Result<[T; 3], &'static str> {
+    let (zero, one) = (T::zero(), T::one());
+    let c = match r {
+        [] => [
+            Complex::new(zero, zero),
+            Complex::new(zero, zero),
+            Complex::new(one, zero),
+        ],
+        [r] => [Complex::new(zero, zero), Complex::new(one, zero), -r],
+        [r0, r1] => [Complex::new(one, zero), -(r0 + r1), r0 * r1],
+        _ => return Err("order too high"),
+    };
+    let eps: T = NumCast::from(1e-9).unwrap();
+    if c.iter().any(|c| c.im.abs() > eps * c.norm()) {
+        return Err("roots not conjugate symmetric");
+    }
+    Ok([c[0].re, c[1].re, c[2].re])
+}
+
+impl<T: Float + Default + Sum<T>> IIR<T> {
+    /// Configures IIR filter coefficients from a continuous-time biquad
+    /// transfer function
+    /// `H(s) = (b[0]*s**2 + b[1]*s + b[2])/(a[0]*s**2 + a[1]*s + a[2])`.
+    ///
+    /// Only `ba` is modified.
+    ///
+    /// # Arguments
+    /// * `b` - Numerator coefficients (descending powers of `s`, `s` in rad per time unit).
+    /// * `a` - Denominator coefficients.
+    /// * `fs` - Sample rate.
+    /// * `transform` - Discretization method.
+    pub fn set_analog(
+        &mut self,
+        b: [T; 3],
+        a: [T; 3],
+        fs: T,
+        transform: Transform<T>,
+    ) -> Result<(), &'static str> {
+        let (zero, one) = (T::zero(), T::one());
+        let two = one + one;
+        let pi: T = NumCast::from(PI).unwrap();
+        if !(fs > zero && fs.is_finite()) {
+            return Err("invalid sample rate");
+        }
+        if a.iter().all(|a| *a == zero) {
+            return Err("invalid denominator");
+        }
+        let (b, a) = match transform {
+            Transform::Tustin | Transform::Prewarp(_) => {
+                let k = if let Transform::Prewarp(f) = transform {
+                    if !(f > zero && f < fs / two) {
+                        return Err("frequency out of range");
+                    }
+                    two * pi * f / (pi * f / fs).tan()
+                } else {
+                    two * fs
+                };
+                let (m, p) = ([one, -one], [one, one]);
+                // Multiply through by the lowest sufficient power of `(1 + z^-1)`
+                // to avoid a spurious pole-zero pair at Nyquist.
+                let order = if a[0] != zero || b[0] != zero {
+                    2
+                } else if a[1] != zero || b[1] != zero {
+                    1
+                } else {
+                    0
+                };
+                let map = |c: [T; 3]| match order {
+                    2 => polyadd(
+                        polyadd(
+                            polyscale(polymul(m, m), c[0] * k * k),
+                            polyscale(polymul(m, p), c[1] * k),
+                        ),
+                        polyscale(polymul(p, p), c[2]),
+                    ),
+                    1 => [c[1] * k + c[2], c[2] - c[1] * k, zero],
+                    _ => [c[2], zero, zero],
+                };
+                (map(b), map(a))
+            }
+            Transform::MatchedZ(f) => {
+                if !(f >= zero && f < fs / two) {
+                    return Err("frequency out of range");
+                }
+                // Polynomial in z^-1 with the mapped finite roots
+                let map = |c: [T; 3]| {
+                    let r = roots(c);
+                    let mut q = [Complex::new(zero, zero); 2];
+                    let mut n = 0;
+                    for r in r.iter().filter(|r| r.re.is_finite() && r.im.is_finite()) {
+                        q[n] = (r / fs).exp();
+                        n += 1;
+                    }
+                    match n {
+                        0 => [one, zero, zero],
+                        1 => [one, -q[0].re, zero],
+                        _ => [one, -(q[0] + q[1]).re, (q[0] * q[1]).re],
+                    }
+                };
+                let (bz, az) = (map(b), map(a));
+                // Match the gain
+                let eval = |c: [T; 3], x: Complex<T>| (x * c[0] + c[1]) * x + c[2];
+                let s = Complex::new(zero, two * pi * f);
+                let ha = eval(b, s) / eval(a, s);
+                let zinv = Complex::from_polar(one, -two * pi * f / fs);
+                let hd = eval([bz[2], bz[1], bz[0]], zinv) / eval([az[2], az[1], az[0]], zinv);
+                let g = ha / hd;
+                if !(g.norm().is_finite() && g.norm() > zero) {
+                    return Err("gain not matchable");
+                }
+                let k = if g.re < zero { -g.norm() } else { g.norm() };
+                (polyscale(bz, k), az)
+            }
+        };
+        if a[0] == zero {
+            return Err("invalid denominator");
+        }
+        self.set_ba(b, a);
+        Ok(())
+    }
+
+    /// Configures IIR filter coefficients from a continuous-time zero-pole-gain
+    /// description `H(s) = k*(s - z[0])*(s - z[1])/((s - p[0])*(s - p[1]))`.
+    ///
+    /// Complex zeros and poles must come in conjugate pairs. See `set_analog()`.
+    ///
+    /// # Arguments
+    /// * `z` - Zeros (up to two).
+    /// * `p` - Poles (up to two).
+    /// * `k` - Gain.
+    /// * `fs` - Sample rate.
+    /// * `transform` - Discretization method.
+    pub fn set_zpk(
+        &mut self,
+        z: &[Complex<T>],
+        p: &[Complex<T>],
+        k: T,
+        fs: T,
+        transform: Transform<T>,
+    ) -> Result<(), &'static str> {
+        let b = poly(z)?;
+        let a = poly(p)?;
+        self.set_analog([b[0] * k, b[1] * k, b[2] * k], a, fs, transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iir::{FrequencyResponse, Shape};
+    use crate::testing::isclose;
+
+    #[test]
+    fn tustin() {
+        let (fs, f0, q) = (1e3, 123., 3.);
+        let w = 2. * PI * f0;
+        let mut i = IIR::<f64>::default();
+        i.set_analog(
+            [0., 0., w * w],
+            [1., w / q, w * w],
+            fs,
+            Transform::Prewarp(f0),
+        )
+        .unwrap();
+        let mut j = IIR::<f64>::default();
+        j.set_lowpass(f0 / fs, Shape::Q(q), 1.).unwrap();
+        for (i, j) in i.ba.iter().zip(j.ba.iter()) {
+            assert!(isclose(*i, *j, 0., 1e-12));
+        }
+        // zpk
+        let p = Complex::new(-w / (2. * q), w * (1. - 1. / (4. * q * q)).sqrt());
+        let mut k = IIR::<f64>::default();
+        k.set_zpk(&[], &[p, p.conj()], w * w, fs, Transform::Prewarp(f0))
+            .unwrap();
+        for (i, k) in i.ba.iter().zip(k.ba.iter()) {
+            assert!(isclose(*i, *k, 0., 1e-12));
+        }
+        assert!(k.set_zpk(&[], &[p, p], 1., fs, Transform::Tustin).is_err());
+
+        // PI: kp + ki/s
+        let (kp, ki) = (2., 300.);
+        i.set_analog([0., kp, ki], [0., 1., 0.], fs, Transform::Tustin)
+            .unwrap();
+        let c = ki / (2. * fs);
+        assert_eq!(i.ba, [kp + c, c - kp, 0., 1., 0.]);
+    }
+
+    #[test]
+    fn matched_z() {
+        let fs = 1e3;
+        // First order lowpass
+        let w = 2. * PI * 10.;
+        let mut i = IIR::<f64>::default();
+        i.set_zpk(&[], &[Complex::new(-w, 0.)], w, fs, Transform::MatchedZ(0.))
+            .unwrap();
+        let p = (-w / fs).exp();
+        assert!(isclose(i.ba[3], p, 0., 1e-15));
+        assert!(isclose(i.ba[0], 1. - p, 0., 1e-15));
+        assert_eq!(&i.ba[1..3], &[0., 0.]);
+        assert_eq!(i.ba[4], 0.);
+
+        // Resonance: poles map exactly, gain matched at the given frequency
+        let p = Complex::new(-20., 2. * PI * 100.);
+        let (b, a) = ([0., 1., 0.], [1., -2. * p.re, p.norm_sqr()]);
+        i.set_analog(b, a, fs, Transform::MatchedZ(100.)).unwrap();
+        let q = (p / fs).exp();
+        let poles = i.poles();
+        assert!(isclose(
+            (poles[0] - q).norm().min((poles[1] - q).norm()),
+            0.,
+            0.,
+            1e-12
+        ));
+        assert!(isclose(i.zeros()[0].re, 1., 0., 1e-12));
+        let s = Complex::new(0., 2. * PI * 100.);
+        let ha = (s * b[1]) / ((s + a[1]) * s + a[2]);
+        assert!(isclose(i.response(0.1).norm(), ha.norm(), 1e-12, 0.));
+        // No gain at DC
+        assert!(i.set_analog(b, a, fs, Transform::MatchedZ(0.)).is_err());
+    }
+}
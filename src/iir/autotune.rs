@@ -0,0 +1,328 @@
+use super::IIR;
+use core::f64::consts::PI;
+use core::iter::Sum;
+use miniconf::MiniconfAtomic;
+use num_traits::{Float, NumCast};
+use serde::{Deserialize, Serialize};
+
+/// Relay feedback experiment configuration.
+///
+/// The relay output is `bias + amplitude` while the plant output is below
+/// `setpoint - hysteresis` and `bias - amplitude` while it is above
+/// `setpoint + hysteresis`. In between the relay keeps its state.
+///
+/// # Miniconf
+///
+/// `{"setpoint": r, "amplitude": d, "bias": u0, "hysteresis": eps, "settle": n, "cycles": m}`
+///
+/// * `r` is the plant output around which the limit cycle is excited.
+/// * `d` is the relay amplitude. Negative for plants with negative gain.
+/// * `u0` is the relay bias. A bias close to the plant input required to reach
+///   the setpoint keeps the limit cycle symmetric and improves the estimates.
+/// * `eps` is the hysteresis. It should exceed the measurement noise.
+/// * `n` is the number of initial limit cycle periods to discard.
+/// * `m` is the number of limit cycle periods to average.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, MiniconfAtomic)]
+pub struct Relay<T> {
+    pub setpoint: T,
+    pub amplitude: T,
+    pub bias: T,
+    pub hysteresis: T,
+    pub settle: u32,
+    pub cycles: u32,
+}
+
+/// PI/PID tuning rule.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Rule {
+    /// Ziegler-Nichols PI: `Kp = 0.45 Ku`, `Ti = Tu/1.2`.
+    ZieglerNicholsPi,
+    /// Ziegler-Nichols PID: `Kp = 0.6 Ku`, `Ti = Tu/2`, `Td = Tu/8`.
+    ZieglerNicholsPid,
+    /// SIMC PI with `tau_c = theta` for the first order plus dead time model
+    /// (gain `K`, time constant `tau`, dead time `theta`) matching the
+    /// static gain and the ultimate point: `Kp = tau/(2 K theta)`, `Ti = min(tau, 8 theta)`.
+    SimcPi,
+}
+
+/// Relay feedback measurement result.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ultimate<T> {
+    /// Ultimate gain estimate from the describing function of the relay.
+    pub ku: T,
+    /// Ultimate period in samples.
+    pub tu: T,
+    /// Static plant gain estimate (ratio of the mean plant output and input).
+    /// Only meaningful if the setpoint differs from the operating point.
+    pub k: T,
+}
+
+/// Controller gains in the `IIR::set_pid()` convention.
+///
+/// The integral and derivative gains are those of the bilinear transform:
+/// `ki = Kp/(2 Ti)`, `kd = 2 Kp Td` with `Ti` and `Td` in samples.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Gains<T> {
+    pub kp: T,
+    pub ki: T,
+    pub kd: T,
+}
+
+/// Relay feedback autotuner.
+///
+/// Drives the plant with a relay around the setpoint (see `Relay`), measures the
+/// amplitude and period of the resulting limit cycle and estimates the ultimate
+/// gain and period. It also estimates the static plant gain.
+///
+/// The relay output and the plant output are deviations from an operating point
+/// where the plant is at rest. The first plant output sample defines that operating
+/// point.
+#[derive(Copy, Clone, Debug)]
+pub struct Autotune<T> {
+    relay: Relay<T>,
+    // Current relay state
+    high: bool,
+    // Sample counter
+    n: u32,
+    // Operating point output
+    y0: T,
+    // Sample index of the last rising relay edge
+    rise: Option<u32>,
+    // Number of completed limit cycle periods
+    periods: u32,
+    y_min: T,
+    y_max: T,
+    // Accumulated periods, amplitudes, inputs, and outputs
+    tu: T,
+    a: T,
+    u: T,
+    y: T,
+    result: Option<Ultimate<T>>,
+}
+
+impl<T: Float> Autotune<T> {
+    pub fn new(relay: Relay<T>) -> Self {
+        Self {
+            relay,
+            high: false,
+            n: 0,
+            y0: T::zero(),
+            rise: None,
+            periods: 0,
+            y_min: T::zero(),
+            y_max: T::zero(),
+            tu: T::zero(),
+            a: T::zero(),
+            u: T::zero(),
+            y: T::zero(),
+            result: None,
+        }
+    }
+
+    /// The measurement result once `settle + cycles` periods have completed.
+    pub fn result(&self) -> Option<Ultimate<T>> {
+        self.result
+    }
+
+    /// Update the relay with a new plant output and return the new plant input.
+    ///
+    /// After the measurement has completed, the plant input is zero.
+    ///
+    /// # Arguments
+    /// * `y` - Plant output.
+    pub fn update(&mut self, y: T) -> T {
+        if self.result.is_some() {
+            return T::zero();
+        }
+        let r = &self.relay;
+        if self.n == 0 {
+            self.y0 = y;
+        }
+        let e = r.setpoint - y;
+        let high = if e > r.hysteresis {
+            true
+        } else if e < -r.hysteresis {
+            false
+        } else {
+            self.high
+        };
+        if high && !self.high {
+            // Rising edge: limit cycle period boundary
+            if let Some(rise) = self.rise {
+                self.periods += 1;
+                if self.periods > r.settle {
+                    self.tu = self.tu + NumCast::from(self.n - rise).unwrap();
+                    self.a = self.a + (self.y_max - self.y_min) / (T::one() + T::one());
+                }
+                if self.periods >= r.settle + r.cycles.max(1) {
+                    self.result = Some(self.finish());
+                    return T::zero();
+                }
+            }
+            self.rise = Some(self.n);
+            self.y_min = y;
+            self.y_max = y;
+        }
+        self.high = high;
+        self.y_min = self.y_min.min(y);
+        self.y_max = self.y_max.max(y);
+        let u = if high {
+            self.relay.bias + self.relay.amplitude
+        } else {
+            self.relay.bias - self.relay.amplitude
+        };
+        if self.rise.is_some() && self.periods >= self.relay.settle {
+            self.u = self.u + u;
+            self.y = self.y + (y - self.y0);
+        }
+        self.n += 1;
+        u
+    }
+
+    fn finish(&self) -> Ultimate<T> {
+        let r = &self.relay;
+        let m: T = NumCast::from(r.cycles.max(1)).unwrap();
+        let a = self.a / m;
+        let pi: T = NumCast::from(PI).unwrap();
+        let four: T = NumCast::from(4).unwrap();
+        Ultimate {
+            // Describing function of the relay with hysteresis
+            ku: four * r.amplitude / (pi * (a * a - r.hysteresis * r.hysteresis).sqrt()),
+            tu: self.tu / m,
+            k: self.y / self.u,
+        }
+    }
+}
+
+impl<T: Float> Ultimate<T> {
+    /// Compute controller gains using the given rule.
+    pub fn gains(&self, rule: Rule) -> Result<Gains<T>, &'static str> {
+        if !(self.ku.is_finite() && self.tu > T::zero()) {
+            return Err("invalid limit cycle");
+        }
+        let c = |x: f64| -> T { NumCast::from(x).unwrap() };
+        let (kp, ti, td) = match rule {
+            Rule::ZieglerNicholsPi => (c(0.45) * self.ku, self.tu / c(1.2), T::zero()),
+            Rule::ZieglerNicholsPid => (c(0.6) * self.ku, self.tu / c(2.), self.tu / c(8.)),
+            Rule::SimcPi => {
+                let k = self.k;
+                let kku = k * self.ku;
+                if !(kku > T::one() && kku.is_finite()) {
+                    return Err("inconsistent model");
+                }
+                let w = c(2. * PI) / self.tu;
+                let tau = (kku * kku - T::one()).sqrt() / w;
+                let theta = (c(PI) - (tau * w).atan()) / w;
+                (tau / (c(2.) * k * theta), tau.min(c(8.) * theta), T::zero())
+            }
+        };
+        Ok(Gains {
+            kp,
+            ki: kp / (c(2.) * ti),
+            kd: c(2.) * kp * td,
+        })
+    }
+}
+
+impl<T: Float + Default + Sum<T>> Gains<T> {
+    /// Configure the filter using `IIR::set_pi()` or, with derivative gain,
+    /// `IIR::set_pid()`.
+    ///
+    /// # Arguments
+    /// * `iir` - Filter to configure.
+    /// * `gi` - Integral gain limit. Zero for an unlimited integrator.
+    /// * `gd` - Derivative gain limit.
+    pub fn apply(&self, iir: &mut IIR<T>, gi: T, gd: T) -> Result<(), &'static str> {
+        if self.kd == T::zero() {
+            iir.set_pi(self.kp, self.ki, gi)
+        } else {
+            iir.set_pid(self.kp, self.ki, self.kd, gi, gd)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{isclose, Fopdt};
+
+    fn relay(plant: &mut Fopdt, relay: Relay<f64>) -> Ultimate<f64> {
+        let mut tune = Autotune::new(relay);
+        for _ in 0..100_000 {
+            let u = tune.update(plant.output());
+            if let Some(res) = tune.result() {
+                return res;
+            }
+            plant.update(u);
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn measure() {
+        let mut plant = Fopdt::new(2., 0.95, 5);
+        let (ku, tu) = plant.ultimate();
+        let res = relay(
+            &mut plant,
+            Relay {
+                setpoint: 1.,
+                amplitude: 0.5,
+                bias: 0.5,
+                hysteresis: 0.01,
+                settle: 3,
+                cycles: 5,
+            },
+        );
+        assert!(isclose(res.tu, tu, 0.15, 0.));
+        assert!(isclose(res.ku, ku, 0.25, 0.));
+        assert!(isclose(res.k, 2., 0.05, 0.));
+    }
+
+    #[test]
+    fn closed_loop() {
+        for rule in [
+            Rule::ZieglerNicholsPi,
+            Rule::ZieglerNicholsPid,
+            Rule::SimcPi,
+        ]
+        .iter()
+        {
+            let mut plant = Fopdt::new(-0.5, 0.98, 8);
+            let res = relay(
+                &mut plant,
+                Relay {
+                    setpoint: -0.2,
+                    amplitude: -1.,
+                    bias: 0.3,
+                    hysteresis: 0.005,
+                    settle: 2,
+                    cycles: 4,
+                },
+            );
+            let g = res.gains(*rule).unwrap();
+            assert!(g.kp < 0.);
+            let mut iir = IIR::new(1., -10., 10.);
+            g.apply(&mut iir, 0., 10. * g.kp).unwrap();
+            // Step response
+            let mut plant = Fopdt::new(-0.5, 0.98, 8);
+            let mut xy = [0.; 5];
+            let r = 0.4;
+            let mut y_max = 0f64;
+            for _ in 0..3000 {
+                let u = iir.update(&mut xy, r - plant.output(), false);
+                y_max = y_max.max(plant.update(u));
+            }
+            assert!(isclose(plant.output(), r, 1e-3, 0.));
+            // Ziegler-Nichols is aggressive
+            let overshoot = if *rule == Rule::SimcPi { 0.2 } else { 0.8 };
+            assert!(y_max < r * (1. + overshoot));
+        }
+        assert!(Ultimate {
+            ku: 1.,
+            tu: 10.,
+            k: 0.5
+        }
+        .gains(Rule::SimcPi)
+        .is_err());
+    }
+}
@@ -0,0 +1,621 @@
+//! High order filter design
+//!
+//! Analog prototypes (Butterworth, Chebyshev type I and II, elliptic (Cauer), and Bessel)
+//! of arbitrary order are transformed to lowpass or highpass responses and discretized
+//! using the bilinear transform with prewarping of the corner frequency.
+//! The result is an array of second-order sections as `iir::IIR` or quantized
+//! `iir_int::IIR`. Their coefficients can also be used in an `iir::Cascade`.
+//!
+//! The sections are constructed for robustness in fixed point implementations:
+//!
+//! * Each pole pair is paired with the nearest zero pair, starting with the poles
+//!   closest to the unit circle.
+//! * Each section has unity gain in the passband (at DC for lowpass and at
+//!   Nyquist for highpass responses). The passband gain correction for even order
+//!   ripple designs is applied to the first section.
+//! * The sections are ordered by increasing pole radius: the least resonant
+//!   section comes first, the most resonant last.
+//!
+//! The conventions for the corner frequency and the ripple parameters follow SciPy
+//! (`scipy.signal.iirfilter()`, Bessel with `norm="phase"`).
+//!
+//! ```
+//! use idsp::iir::design::{sos, Prototype, Response};
+//! // 5th order Butterworth lowpass with -3 dB at 0.1 of the sample rate
+//! let sections = sos::<3>(Prototype::Butterworth, Response::Lowpass, 5, 0.1).unwrap();
+//! ```
+
+use super::{Vec5, IIR};
+use crate::{iir_int, Complex};
+use core::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+
+// f64 math in `no_std`
+#[allow(unused_imports)]
+use num_traits::Float;
+
+/// Analog lowpass prototype filter family.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Prototype {
+    /// Maximally flat magnitude. -3 dB at the corner frequency.
+    Butterworth,
+    /// Equiripple passband with `rp` dB ripple, monotonic stopband.
+    /// The gain at the corner frequency is `-rp` dB.
+    ChebyshevI { rp: f64 },
+    /// Monotonic passband, equiripple stopband with at least `rs` dB attenuation.
+    /// The gain at the corner frequency (the stopband edge) is `-rs` dB.
+    ChebyshevII { rs: f64 },
+    /// Equiripple passband with `rp` dB ripple and equiripple stopband with
+    /// `rs` dB attenuation. The gain at the corner frequency is `-rp` dB.
+    Elliptic { rp: f64, rs: f64 },
+    /// Maximally flat group delay. The phase shift at the corner frequency
+    /// is half the total phase shift.
+    Bessel,
+}
+
+/// Filter response type.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum Response {
+    Lowpass,
+    Highpass,
+}
+
+/// Analog lowpass prototype poles and zeros.
+///
+/// Only one of each complex conjugate pair is stored (upper half plane).
+/// A real pole (odd order) occupies a single slot.
+/// Zeros not stored are at infinity.
+struct Zpk<const M: usize> {
+    p: [Complex<f64>; M],
+    np: usize,
+    z: [Complex<f64>; M],
+    nz: usize,
+    /// Passband gain
+    k: f64,
+}
+
+impl<const M: usize> Zpk<M> {
+    fn new(k: f64) -> Self {
+        Self {
+            p: [Complex::new(0., 0.); M],
+            np: 0,
+            z: [Complex::new(0., 0.); M],
+            nz: 0,
+            k,
+        }
+    }
+
+    fn push_pole(&mut self, p: Complex<f64>) {
+        self.p[self.np] = p;
+        self.np += 1;
+    }
+
+    fn push_zero(&mut self, z: Complex<f64>) {
+        self.z[self.nz] = z;
+        self.nz += 1;
+    }
+
+    /// Poles of a Chebyshev type I style prototype with given `mu` (Butterworth for
+    /// `mu = asinh(1)`, scaled).
+    fn chebyshev(order: usize, mu: f64, k: f64) -> Self {
+        let mut zpk = Self::new(k);
+        let (sinh, cosh) = (mu.sinh(), mu.cosh());
+        for i in 0..order / 2 {
+            let (s, c) = (PI * (2 * i + 1) as f64 / (2 * order) as f64).sin_cos();
+            zpk.push_pole(Complex::new(-sinh * s, cosh * c));
+        }
+        if order & 1 != 0 {
+            zpk.push_pole(Complex::new(-sinh, 0.));
+        }
+        zpk
+    }
+
+    fn butterworth(order: usize) -> Self {
+        let mut zpk = Self::new(1.);
+        for i in 0..order / 2 {
+            let (s, c) = (PI * (2 * i + 1) as f64 / (2 * order) as f64).sin_cos();
+            zpk.push_pole(Complex::new(-s, c));
+        }
+        if order & 1 != 0 {
+            zpk.push_pole(Complex::new(-1., 0.));
+        }
+        zpk
+    }
+
+    fn chebyshev1(order: usize, rp: f64) -> Self {
+        let eps2 = 10f64.powf(0.1 * rp) - 1.;
+        let mu = (1. / eps2.sqrt()).asinh() / order as f64;
+        let k = if order & 1 == 0 {
+            1. / (1. + eps2).sqrt()
+        } else {
+            1.
+        };
+        Self::chebyshev(order, mu, k)
+    }
+
+    fn chebyshev2(order: usize, rs: f64) -> Self {
+        let eps = 1. / (10f64.powf(0.1 * rs) - 1.).sqrt();
+        let mu = (1. / eps).asinh() / order as f64;
+        let mut zpk = Self::chebyshev(order, mu, 1.);
+        for p in zpk.p[..zpk.np].iter_mut() {
+            *p = p.conj().inv();
+        }
+        for i in 0..order / 2 {
+            let c = (PI * (2 * i + 1) as f64 / (2 * order) as f64).cos();
+            zpk.push_zero(Complex::new(0., 1. / c));
+        }
+        zpk
+    }
+
+    fn elliptic(order: usize, rp: f64, rs: f64) -> Self {
+        if order == 1 {
+            return Self::chebyshev1(order, rp);
+        }
+        let n = order as f64;
+        let eps2 = 10f64.powf(0.1 * rp) - 1.;
+        let k1sq = eps2 / (10f64.powf(0.1 * rs) - 1.);
+        let (k1, k1p) = (ellipk(k1sq), ellipkm1(k1sq));
+        // Solve the degree equation K(m)/K(1 - m) = n*K(k1sq)/K(1 - k1sq)
+        let (m, m1) = ellipdeg(n * k1 / k1p);
+        let capk = ellipkm1(m1);
+        // Inverse of sc(r, 1 - k1sq) = 1/eps
+        let phi = (1. / eps2.sqrt()).atan();
+        let (s, c) = phi.sin_cos();
+        let r = s * carlson_rf(c * c, c * c + k1sq * s * s, 1.);
+        let v0 = capk * r / (n * k1);
+        let (sv, cv, dv) = ellipj(v0, m1, m);
+        let mut zpk = Self::new(if order & 1 == 0 {
+            1. / (1. + eps2).sqrt()
+        } else {
+            1.
+        });
+        for j in (1 - (order & 1)..order).step_by(2) {
+            let (s, c, d) = ellipj(j as f64 * capk / n, m, m1);
+            let den = 1. - (d * sv).powi(2);
+            let p = Complex::new(-c * d * sv * cv / den, s * dv / den);
+            if s.abs() > f64::EPSILON {
+                zpk.push_zero(Complex::new(0., 1. / (m.sqrt() * s)));
+                zpk.push_pole(p);
+            } else {
+                zpk.push_pole(Complex::new(p.re, 0.));
+            }
+        }
+        // Order the real pole last like the other prototypes
+        zpk.p[..zpk.np].rotate_left(order & 1);
+        zpk
+    }
+
+    fn bessel(order: usize) -> Self {
+        let mut zpk = Self::new(1.);
+        // Phase normalization: product of the poles is one
+        let scale = (1..=order).map(|k| ((2 * k - 1) as f64).ln()).sum::<f64>() / order as f64;
+        let scale = scale.exp();
+        let mut roots = [Complex::new(0., 0.); M];
+        let mut nr = 0;
+        if order & 1 != 0 {
+            roots[0] = bessel_root(order, Complex::new(-scale, 0.), &roots[..0]);
+            nr = 1;
+        }
+        for i in 0..order / 2 {
+            let (s, c) = (PI * (2 * i + 1) as f64 / (2 * order) as f64).sin_cos();
+            let r0 = Complex::new(-s, c) * scale;
+            roots[nr] = bessel_root(order, r0, &roots[..nr]);
+            nr += 1;
+        }
+        for r in roots[order & 1..nr].iter() {
+            zpk.push_pole(r / scale);
+        }
+        if order & 1 != 0 {
+            zpk.push_pole(roots[0] / scale);
+        }
+        zpk
+    }
+}
+
+/// Refine a root of the reverse Bessel polynomial of given order
+/// by Newton-Maehly iteration, deflating the roots already found (and their conjugates).
+fn bessel_root(order: usize, mut z: Complex<f64>, found: &[Complex<f64>]) -> Complex<f64> {
+    for _ in 0..100 {
+        // Evaluate polynomial and derivative by the recurrence
+        // q(n) = (2n - 1) q(n - 1) + z^2 q(n - 2)
+        let z2 = z * z;
+        let (mut q0, mut q1) = (Complex::new(1., 0.), z + 1.);
+        let (mut d0, mut d1) = (Complex::new(0., 0.), Complex::new(1., 0.));
+        for n in 2..=order {
+            let k = (2 * n - 1) as f64;
+            let q = q1 * k + z2 * q0;
+            let d = d1 * k + z * q0 * 2. + z2 * d0;
+            q0 = q1;
+            q1 = q;
+            d0 = d1;
+            d1 = d;
+        }
+        if q1.norm_sqr() == 0. {
+            break;
+        }
+        let mut w = d1 / q1;
+        for r in found.iter() {
+            w -= (z - r).inv();
+            if r.im != 0. {
+                w -= (z - r.conj()).inv();
+            }
+        }
+        let dz = w.inv();
+        z -= dz;
+        if dz.norm_sqr() <= 1e-30 * z.norm_sqr() {
+            break;
+        }
+    }
+    z
+}
+
+/// Carlson's symmetric elliptic integral of the first kind
+fn carlson_rf(mut x: f64, mut y: f64, mut z: f64) -> f64 {
+    loop {
+        let mu = (x + y + z) / 3.;
+        let (dx, dy, dz) = (1. - x / mu, 1. - y / mu, 1. - z / mu);
+        if dx.abs().max(dy.abs()).max(dz.abs()) < 1e-3 {
+            let e2 = dx * dy - dz * dz;
+            let e3 = dx * dy * dz;
+            return (1. - e2 / 10. + e3 / 14. + e2 * e2 / 24. - 3. * e2 * e3 / 44.) / mu.sqrt();
+        }
+        let (sx, sy, sz) = (x.sqrt(), y.sqrt(), z.sqrt());
+        let lambda = sx * sy + sy * sz + sz * sx;
+        x = (x + lambda) / 4.;
+        y = (y + lambda) / 4.;
+        z = (z + lambda) / 4.;
+    }
+}
+
+/// Complete elliptic integral of the first kind K(m)
+fn ellipk(m: f64) -> f64 {
+    carlson_rf(0., 1. - m, 1.)
+}
+
+/// K(1 - p), accurate for small `p`
+fn ellipkm1(p: f64) -> f64 {
+    carlson_rf(0., p, 1.)
+}
+
+/// Solve `K(m)/K(1 - m) = ratio` for `m` using the nome.
+///
+/// Returns `(m, 1 - m)`.
+fn ellipdeg(ratio: f64) -> (f64, f64) {
+    let q = (-PI / ratio).exp();
+    // Jacobi theta functions theta2, theta3, theta4
+    let (mut t2, mut t3, mut t4) = (0., 1., 1.);
+    for n in 0..32 {
+        let e = q.powi(n * (n + 1));
+        t2 += e;
+        if n > 0 {
+            let e = q.powi(n * n);
+            t3 += 2. * e;
+            t4 += if n & 1 != 0 { -2. * e } else { 2. * e };
+        }
+        if e < 1e-20 {
+            break;
+        }
+    }
+    let t2 = 2. * q.powf(0.25) * t2;
+    ((t2 / t3).powi(4), (t4 / t3).powi(4))
+}
+
+/// Jacobi elliptic functions `sn, cn, dn` of `u` with parameter `m`
+/// and complementary parameter `m1 = 1 - m`.
+///
+/// Arithmetic-geometric mean method, Abramowitz & Stegun 16.4.
+fn ellipj(u: f64, m: f64, m1: f64) -> (f64, f64, f64) {
+    const N: usize = 16;
+    let mut a = [0.; N];
+    let mut c = [0.; N];
+    a[0] = 1.;
+    let mut b = m1.sqrt();
+    c[0] = m.sqrt();
+    let mut n = 0;
+    while n < N - 1 && c[n].abs() > f64::EPSILON {
+        a[n + 1] = (a[n] + b) / 2.;
+        c[n + 1] = (a[n] - b) / 2.;
+        b = (a[n] * b).sqrt();
+        n += 1;
+    }
+    let mut phi = (1 << n) as f64 * a[n] * u;
+    for i in (1..=n).rev() {
+        phi = (phi + (c[i] / a[i] * phi.sin()).asin()) / 2.;
+    }
+    let (sn, cn) = phi.sin_cos();
+    (sn, cn, (1. - m * sn * sn).sqrt())
+}
+
+/// Compute second-order sections for a digital filter.
+///
+/// # Arguments
+/// * `prototype` - Analog prototype filter family.
+/// * `response` - Lowpass or highpass response.
+/// * `order` - Filter order, `1 <= order <= 2*M`.
+/// * `f` - Corner frequency (in units of sample rate, `0 < f < 0.5`).
+///   See `Prototype` for the respective definitions.
+///
+/// # Returns
+/// `M` biquad sections. Sections not required for the given order are identity.
+/// The output limits are infinite and the offsets are zero.
+pub fn sos<const M: usize>(
+    prototype: Prototype,
+    response: Response,
+    order: usize,
+    f: f64,
+) -> Result<[IIR<f64>; M], &'static str> {
+    if !(1..=2 * M).contains(&order) {
+        return Err("invalid order");
+    }
+    if !(f > 0. && f < 0.5) {
+        return Err("frequency out of range");
+    }
+    let zpk = match prototype {
+        Prototype::Butterworth => Zpk::<M>::butterworth(order),
+        Prototype::ChebyshevI { rp } => {
+            if !(rp > 0. && rp.is_finite()) {
+                return Err("invalid ripple");
+            }
+            Zpk::chebyshev1(order, rp)
+        }
+        Prototype::ChebyshevII { rs } => {
+            if !(rs > 0. && rs.is_finite()) {
+                return Err("invalid attenuation");
+            }
+            Zpk::chebyshev2(order, rs)
+        }
+        Prototype::Elliptic { rp, rs } => {
+            if !(rp > 0. && rs > rp && rs.is_finite()) {
+                return Err("invalid ripple or attenuation");
+            }
+            Zpk::elliptic(order, rp, rs)
+        }
+        Prototype::Bessel => Zpk::bessel(order),
+    };
+    // Prewarped analog corner frequency for the bilinear transform with T = 1
+    let wa = 2. * (PI * f).tan();
+    // Frequency transformation and bilinear transform
+    let map = |s: Complex<f64>| {
+        let s = match response {
+            Response::Lowpass => s * wa,
+            Response::Highpass => s.inv() * wa,
+        };
+        (s + 2.) / (-s + 2.)
+    };
+    // Mapped zero at infinity and passband reference
+    let (z_inf, z_ref) = match response {
+        Response::Lowpass => (-1., 1.),
+        Response::Highpass => (1., -1.),
+    };
+    let mut p = zpk.p;
+    for p in p[..zpk.np].iter_mut() {
+        *p = map(*p);
+    }
+    let mut z = zpk.z;
+    for z in z[..zpk.nz].iter_mut() {
+        *z = map(*z);
+    }
+
+    // Pair: poles closest to the unit circle with the nearest zeros first
+    let mut idx = [0usize; M];
+    for (i, idx) in idx.iter_mut().enumerate() {
+        *idx = i;
+    }
+    let idx = &mut idx[..zpk.np];
+    idx.sort_unstable_by(|i, j| p[*j].norm_sqr().partial_cmp(&p[*i].norm_sqr()).unwrap());
+    let mut used = [false; M];
+    let mut sections = [[0.; 5]; M];
+    for (section, i) in sections.iter_mut().zip(idx.iter()) {
+        let p = p[*i];
+        let real = order & 1 != 0 && *i == zpk.np - 1;
+        let nearest = if real {
+            None
+        } else {
+            (0..zpk.nz).filter(|j| !used[*j]).min_by(|j, k| {
+                (z[*j] - p)
+                    .norm_sqr()
+                    .partial_cmp(&(z[*k] - p).norm_sqr())
+                    .unwrap()
+            })
+        };
+        let (b, a) = if real {
+            ([1., -z_inf, 0.], [1., -p.re, 0.])
+        } else {
+            let z = match nearest {
+                Some(j) => {
+                    used[j] = true;
+                    z[j]
+                }
+                None => Complex::new(z_inf, 0.),
+            };
+            (
+                [1., -2. * z.re, z.norm_sqr()],
+                [1., -2. * p.re, p.norm_sqr()],
+            )
+        };
+        // Unity passband gain
+        let g = (a[0] + z_ref * (a[1] + z_ref * a[2])) / (b[0] + z_ref * (b[1] + z_ref * b[2]));
+        *section = [g * b[0], g * b[1], g * b[2], -a[1], -a[2]];
+    }
+    // Least resonant first
+    sections[..zpk.np].reverse();
+    for b in sections[0][..3].iter_mut() {
+        *b *= zpk.k;
+    }
+    for section in sections[zpk.np..].iter_mut() {
+        *section = [1., 0., 0., 0., 0.];
+    }
+    let mut iir = [IIR::new(1., f64::NEG_INFINITY, f64::INFINITY); M];
+    for (iir, ba) in iir.iter_mut().zip(sections.iter()) {
+        iir.ba = *ba as Vec5<f64>;
+    }
+    Ok(iir)
+}
+
+/// Compute quantized second-order sections for a digital filter.
+///
+/// See `sos()`. The coefficients are quantized using `iir_int::IIR::set_ba()`.
+/// The output limits are the `i32` range and the offsets are zero.
+pub fn sos_int<const M: usize>(
+    prototype: Prototype,
+    response: Response,
+    order: usize,
+    f: f64,
+) -> Result<[iir_int::IIR; M], &'static str> {
+    let sos = sos::<M>(prototype, response, order, f)?;
+    let mut iir = [iir_int::IIR {
+        y_min: i32::MIN,
+        y_max: i32::MAX,
+        ..Default::default()
+    }; M];
+    for (iir, sos) in iir.iter_mut().zip(sos.iter()) {
+        iir.set_ba(&sos.ba)?;
+    }
+    Ok(iir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iir::FrequencyResponse;
+    use crate::testing::isclose;
+
+    fn db(x: f64) -> f64 {
+        10f64.powf(x / 20.)
+    }
+
+    #[test]
+    fn butterworth() {
+        for order in 1..=8 {
+            for (response, f) in [(Response::Lowpass, 0.1), (Response::Highpass, 0.03)].iter() {
+                let s = sos::<4>(Prototype::Butterworth, *response, order, *f).unwrap();
+                assert!(isclose(s.response(*f).norm(), 0.5f64.sqrt(), 1e-9, 0.));
+                let f_pass = if *response == Response::Lowpass {
+                    0.
+                } else {
+                    0.5
+                };
+                assert!(isclose(s.response(f_pass).norm(), 1., 1e-12, 0.));
+                assert!(s.response(0.5 - f_pass).norm() < 1e-12);
+            }
+        }
+        assert!(sos::<2>(Prototype::Butterworth, Response::Lowpass, 5, 0.1).is_err());
+        assert!(sos::<2>(Prototype::Butterworth, Response::Lowpass, 0, 0.1).is_err());
+        assert!(sos::<2>(Prototype::Butterworth, Response::Lowpass, 2, 0.5).is_err());
+    }
+
+    #[test]
+    fn chebyshev() {
+        for order in 1..=7 {
+            let rp = 0.5;
+            let f = 0.05;
+            let s = sos::<4>(Prototype::ChebyshevI { rp }, Response::Lowpass, order, f).unwrap();
+            assert!(isclose(s.response(f).norm(), db(-rp), 1e-9, 0.));
+            let (mut min, mut max) = (1f64, 0f64);
+            for i in 0..=1000 {
+                let g = s.response(f * i as f64 / 1000.).norm();
+                min = min.min(g);
+                max = max.max(g);
+            }
+            assert!(isclose(max, 1., 1e-6, 0.));
+            assert!(isclose(min, db(-rp), 1e-6, 0.));
+
+            let rs = 40.;
+            let s = sos::<4>(Prototype::ChebyshevII { rs }, Response::Highpass, order, f).unwrap();
+            assert!(isclose(s.response(f).norm(), db(-rs), 1e-9, 0.));
+            assert!(isclose(s.response(0.5).norm(), 1., 1e-12, 0.));
+            for i in 0..=1000 {
+                assert!(s.response(f * i as f64 / 1000.).norm() <= db(-rs) * (1. + 1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn elliptic() {
+        for order in 1..=8 {
+            let (rp, rs) = (1., 60.);
+            let f = 0.1;
+            let s = sos::<4>(Prototype::Elliptic { rp, rs }, Response::Lowpass, order, f).unwrap();
+            assert!(isclose(s.response(f).norm(), db(-rp), 1e-9, 0.));
+            let (mut min, mut max) = (1f64, 0f64);
+            for i in 0..=1000 {
+                let g = s.response(f * i as f64 / 1000.).norm();
+                min = min.min(g);
+                max = max.max(g);
+            }
+            assert!(isclose(max, 1., 1e-6, 0.));
+            assert!(isclose(min, db(-rp), 1e-6, 0.));
+            if order >= 5 {
+                // Equiripple stopband
+                let mut max = 0f64;
+                for i in 0..=1000 {
+                    max = max.max(s.response(0.2 + 0.3 * i as f64 / 1000.).norm());
+                }
+                assert!(max <= db(-rs) * (1. + 1e-9));
+                assert!(isclose(max, db(-rs), 1e-4, 0.));
+            }
+        }
+    }
+
+    #[test]
+    fn bessel() {
+        // Roots of s^4 + 10 s^3 + 45 s^2 + 105 s + 105, normalized by 105^(1/4)
+        let zpk = Zpk::<2>::bessel(4);
+        let want = [Complex::new(-0.6572, 0.8302), Complex::new(-0.9047, 0.2709)];
+        let scale = 105f64.powf(0.25);
+        for (p, w) in zpk.p.iter().zip(want.iter()) {
+            assert!((p - w).norm_sqr() < 1e-8, "{} {}", p, w);
+            let s = p * scale;
+            let q = (((s + 10.) * s + 45.) * s + 105.) * s + 105.;
+            assert!(q.norm_sqr() < 1e-20);
+        }
+        for order in 1..=12 {
+            let zpk = Zpk::<6>::bessel(order);
+            let prod = zpk.p[..zpk.np].iter().fold(Complex::new(1., 0.), |a, p| {
+                if p.im == 0. {
+                    a * -p
+                } else {
+                    a * p.norm_sqr()
+                }
+            });
+            assert!((prod - 1.).norm_sqr() < 1e-18);
+            let s = sos::<6>(Prototype::Bessel, Response::Lowpass, order, 0.1).unwrap();
+            assert!(isclose(s.response(0.).norm(), 1., 1e-12, 0.));
+        }
+    }
+
+    #[test]
+    fn ordering() {
+        let s = sos::<4>(
+            Prototype::Elliptic { rp: 0.1, rs: 80. },
+            Response::Lowpass,
+            7,
+            0.05,
+        )
+        .unwrap();
+        for s in s.windows(2) {
+            assert!(-s[0].ba[4] <= -s[1].ba[4]);
+        }
+        for s in s[1..].iter() {
+            assert!(isclose(
+                s.ba[..3].iter().sum::<f64>(),
+                1. - s.ba[3] - s.ba[4],
+                1e-12,
+                0.
+            ));
+        }
+        let si = sos_int::<4>(
+            Prototype::Elliptic { rp: 0.1, rs: 80. },
+            Response::Lowpass,
+            7,
+            0.05,
+        )
+        .unwrap();
+        for (s, si) in s.iter().zip(si.iter()) {
+            for (c, ci) in s.ba.iter().zip(si.ba.iter()) {
+                assert!((c * (1 << iir_int::IIR::SHIFT) as f64 - *ci as f64).abs() <= 0.5);
+            }
+            assert_eq!((si.y_min, si.y_max), (i32::MIN, i32::MAX));
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use super::{abs, macc, FrequencyResponse, IIR};
+use crate::Complex;
+use core::f64::consts::PI;
+use core::iter::Sum;
+use miniconf::Miniconf;
+use num_traits::{Float, NumCast};
+
+/// `IIRN` state type.
+///
+/// `xy[0]` contains the `N` previous inputs `[x1, .., xN]` and `xy[1]` the `N`
+/// previous outputs `[y1, .., yN]`. Lower indices correspond to more recent samples.
+pub type StateN<T, const N: usize> = [[T; N]; 2];
+
+/// Direct form I IIR filter of order `N`.
+///
+/// Generalization of `IIR` (`N = 2`) to `2N + 1` coefficients. The new output is
+/// `y0 = y_offset + b0*x0 + b[0]*x1 + .. + b[N - 1]*xN + a[0]*y1 + .. + a[N - 1]*yN`.
+/// As for `IIR` the feed-back coefficients `a` are negated and normalized such
+/// that `a0 = 1`.
+///
+/// Offset, output limits and hold behave like for `IIR`. Higher order sections
+/// are more sensitive to coefficient errors than a `Cascade` of biquads.
+/// `N` must be at least one.
+///
+/// # Miniconf
+///
+/// `{"b0": b0, "b": [b1, .., bN], "a": [a1, .., aN], "y_offset": y_offset, "y_min": y_min, "y_max": y_max}`
+#[derive(Copy, Clone, Debug, Miniconf)]
+pub struct IIRN<T, const N: usize> {
+    pub b0: T,
+    pub b: [T; N],
+    pub a: [T; N],
+    pub y_offset: T,
+    pub y_min: T,
+    pub y_max: T,
+}
+
+impl<T: Default + Copy, const N: usize> Default for IIRN<T, N> {
+    fn default() -> Self {
+        Self {
+            b0: T::default(),
+            b: [T::default(); N],
+            a: [T::default(); N],
+            y_offset: T::default(),
+            y_min: T::default(),
+            y_max: T::default(),
+        }
+    }
+}
+
+impl<T: Float + Default + Sum<T>, const N: usize> IIRN<T, N> {
+    pub fn new(gain: T, y_min: T, y_max: T) -> Self {
+        Self {
+            b0: gain,
+            y_min,
+            y_max,
+            ..Default::default()
+        }
+    }
+
+    /// Return the feed-forward gain `b0 + b1 + .. + bN`.
+    pub fn get_k(&self) -> T {
+        self.b0 + self.b.iter().copied().sum()
+    }
+
+    /// Compute input-referred (`x`) offset from output (`y`) offset.
+    pub fn get_x_offset(&self) -> Result<T, &str> {
+        let k = self.get_k();
+        if abs(k) < T::epsilon() {
+            Err("k is zero")
+        } else {
+            Ok(self.y_offset / k)
+        }
+    }
+
+    /// Convert input (`x`) offset to equivalent output (`y`) offset and apply.
+    ///
+    /// # Arguments
+    /// * `xo`: Input (`x`) offset.
+    pub fn set_x_offset(&mut self, xo: T) {
+        self.y_offset = xo * self.get_k();
+    }
+
+    /// Feed a new input value into the filter, update the filter state, and
+    /// return the new output. Only the state `xy` is modified.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the output, see `IIR::update()`.
+    pub fn update(&self, xy: &mut StateN<T, N>, x0: T, hold: bool) -> T {
+        debug_assert!(N > 0);
+        let y0 = if hold {
+            xy[1][0]
+        } else {
+            let y0 = macc(self.y_offset + self.b0 * x0, &xy[0], &self.b);
+            macc(y0, &xy[1], &self.a)
+        };
+        let y0 = y0.max(self.y_min).min(self.y_max);
+        xy[0].copy_within(0..N - 1, 1);
+        xy[0][0] = x0;
+        xy[1].copy_within(0..N - 1, 1);
+        xy[1][0] = y0;
+        y0
+    }
+}
+
+impl<T: Copy + Default> From<IIR<T>> for IIRN<T, 2> {
+    fn from(iir: IIR<T>) -> Self {
+        let ba = iir.ba;
+        Self {
+            b0: ba[0],
+            b: [ba[1], ba[2]],
+            a: [ba[3], ba[4]],
+            y_offset: iir.y_offset,
+            y_min: iir.y_min,
+            y_max: iir.y_max,
+        }
+    }
+}
+
+impl<T: Float, const N: usize> FrequencyResponse<T> for IIRN<T, N> {
+    fn response(&self, f: T) -> Complex<T> {
+        let z = Complex::from_polar(T::one(), -f * NumCast::from(2. * PI).unwrap());
+        // Horner in z^-1
+        let b = self
+            .b
+            .iter()
+            .rev()
+            .fold(Complex::new(T::zero(), T::zero()), |p, c| (p + *c) * z);
+        let a = self
+            .a
+            .iter()
+            .rev()
+            .fold(Complex::new(T::zero(), T::zero()), |p, c| (p - *c) * z);
+        (b + self.b0) / (a + T::one())
+    }
+
+    fn group_delay(&self, f: T) -> T {
+        let z = Complex::from_polar(T::one(), -f * NumCast::from(2. * PI).unwrap());
+        // Group delay of a polynomial c in z^-1: Re(sum(k*c[k]*z^-k)/sum(c[k]*z^-k))
+        let tau = |c0: T, c: &[T]| {
+            let (mut p, mut dp) = (
+                Complex::new(c0, T::zero()),
+                Complex::new(T::zero(), T::zero()),
+            );
+            let mut zk = Complex::new(T::one(), T::zero());
+            for (k, c) in c.iter().enumerate() {
+                zk = zk * z;
+                p = p + zk * *c;
+                dp = dp + zk * (*c * NumCast::from(k + 1).unwrap());
+            }
+            (dp / p).re
+        };
+        let mut a = self.a;
+        for a in a.iter_mut() {
+            *a = -*a;
+        }
+        tau(self.b0, &self.b) - tau(T::one(), &a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iir::{Cascade, Shape};
+    use crate::testing::isclose;
+
+    #[test]
+    fn biquad() {
+        let mut i = IIR::<f64>::new(1., -2., 3.);
+        i.set_peaking(0.05, Shape::Q(2.), 4.).unwrap();
+        i.y_offset = 0.1;
+        let n = IIRN::from(i);
+        let (mut xy, mut xyn) = ([0.; 5], [[0.; 2]; 2]);
+        for k in 0..200 {
+            let x = ((k * 7) % 11) as f64 * 0.1 - 0.4;
+            let hold = k % 17 == 0;
+            assert_eq!(i.update(&mut xy, x, hold), n.update(&mut xyn, x, hold));
+        }
+        for f in [0., 0.03, 0.05, 0.3].iter() {
+            assert!(isclose(
+                (n.response(*f) - i.response(*f)).norm(),
+                0.,
+                0.,
+                1e-12
+            ));
+            assert!(isclose(n.group_delay(*f), i.group_delay(*f), 1e-9, 1e-12));
+        }
+    }
+
+    #[test]
+    fn fourth_order() {
+        let mut c = Cascade::<f64, 2>::new(1., -10., 10.);
+        let mut i = IIR::<f64>::default();
+        i.set_lowpass(0.02, Shape::Q(0.6), 1.).unwrap();
+        c.ba[0] = i.ba;
+        i.set_lowpass(0.02, Shape::Q(1.3), 1.).unwrap();
+        c.ba[1] = i.ba;
+        // Multiply the two sections
+        let conv = |p: [f64; 3], q: [f64; 3]| {
+            let mut r = [0.; 5];
+            for (j, p) in p.iter().enumerate() {
+                for (k, q) in q.iter().enumerate() {
+                    r[j + k] += p * q;
+                }
+            }
+            r
+        };
+        let pa = |ba: &[f64; 5]| [1., -ba[3], -ba[4]];
+        let pb = |ba: &[f64; 5]| [ba[0], ba[1], ba[2]];
+        let b = conv(pb(&c.ba[0]), pb(&c.ba[1]));
+        let a = conv(pa(&c.ba[0]), pa(&c.ba[1]));
+        let n = IIRN::<f64, 4> {
+            b0: b[0],
+            b: [b[1], b[2], b[3], b[4]],
+            a: [-a[1], -a[2], -a[3], -a[4]],
+            y_offset: 0.,
+            y_min: -10.,
+            y_max: 10.,
+        };
+        assert!(isclose(
+            n.get_k() / (1. - n.a.iter().sum::<f64>()),
+            1.,
+            1e-9,
+            0.
+        ));
+        let (mut xy, mut xyn) = ([[0.; 5]; 2], [[0.; 4]; 2]);
+        for k in 0..2000 {
+            let x = if k < 1000 { 3. } else { -1. };
+            let y = c.update(&mut xy, x, false);
+            let yn = n.update(&mut xyn, x, false);
+            assert!(isclose(y, yn, 1e-9, 1e-9));
+        }
+        // Clamping
+        let mut xyn = [[0.; 4]; 2];
+        assert_eq!(n.update(&mut xyn, 1e7, false), 10.);
+        for _ in 0..1000 {
+            assert!(n.update(&mut xyn, 1e7, false).abs() <= 10.);
+        }
+        for f in [0.001, 0.02, 0.1].iter() {
+            assert!(isclose(
+                (n.response(*f) - c.response(*f)).norm(),
+                0.,
+                0.,
+                1e-9
+            ));
+            assert!(isclose(n.group_delay(*f), c.group_delay(*f), 1e-6, 1e-9));
+        }
+    }
+}
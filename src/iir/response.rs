@@ -0,0 +1,192 @@
+use super::{Cascade, Vec5, IIR};
+use crate::{iir_int, Complex};
+use core::f64::consts::PI;
+use num_traits::{Float, NumCast};
+
+/// Frequency response of a filter.
+///
+/// Frequencies are in units of the sample rate. The integer filters
+/// (`iir_int::IIR`, `iir_int::Cascade`) are evaluated in `f64`.
+/// Offsets and output limits are ignored.
+pub trait FrequencyResponse<T: Copy> {
+    /// Compute the complex gain at the given frequency.
+    fn response(&self, f: T) -> Complex<T>;
+
+    /// Compute the group delay (in samples) at the given frequency.
+    fn group_delay(&self, f: T) -> T;
+
+    /// Compute the complex gains for a grid of frequencies.
+    ///
+    /// # Arguments
+    /// * `f` - Frequencies.
+    /// * `h` - Output. Must be the same length as `f`.
+    fn sweep(&self, f: &[T], h: &mut [Complex<T>]) {
+        debug_assert_eq!(f.len(), h.len());
+        for (f, h) in f.iter().zip(h.iter_mut()) {
+            *h = self.response(*f);
+        }
+    }
+}
+
+/// `z^-1` on the unit circle at the given frequency
+fn zinv<T: Float>(f: T) -> Complex<T> {
+    Complex::from_polar(T::one(), -f * NumCast::from(2. * PI).unwrap())
+}
+
+fn ba_response<T: Float>(ba: &Vec5<T>, f: T) -> Complex<T> {
+    let z = zinv(f);
+    let b = (z * ba[2] + ba[1]) * z + ba[0];
+    let a = -(z * ba[4] + ba[3]) * z + T::one();
+    b / a
+}
+
+fn ba_group_delay<T: Float>(ba: &Vec5<T>, f: T) -> T {
+    let z = zinv(f);
+    let two = T::one() + T::one();
+    // Group delay of a polynomial c in z^-1: Re(sum(k*c[k]*z^-k)/sum(c[k]*z^-k))
+    let tau = |c: [T; 3]| {
+        let p = (z * c[2] + c[1]) * z + c[0];
+        let dp = (z * c[2] * two + c[1]) * z;
+        (dp / p).re
+    };
+    tau([ba[0], ba[1], ba[2]]) - tau([T::one(), -ba[3], -ba[4]])
+}
+
+impl<T: Float> FrequencyResponse<T> for IIR<T> {
+    fn response(&self, f: T) -> Complex<T> {
+        ba_response(&self.ba, f)
+    }
+
+    fn group_delay(&self, f: T) -> T {
+        ba_group_delay(&self.ba, f)
+    }
+}
+
+impl<T: Float> FrequencyResponse<T> for [IIR<T>] {
+    fn response(&self, f: T) -> Complex<T> {
+        self.iter()
+            .fold(Complex::new(T::one(), T::zero()), |h, s| h * s.response(f))
+    }
+
+    fn group_delay(&self, f: T) -> T {
+        self.iter().fold(T::zero(), |t, s| t + s.group_delay(f))
+    }
+}
+
+impl<T: Float, const N: usize> FrequencyResponse<T> for Cascade<T, N> {
+    fn response(&self, f: T) -> Complex<T> {
+        self.ba
+            .iter()
+            .fold(Complex::new(T::one(), T::zero()), |h, ba| {
+                h * ba_response(ba, f)
+            })
+    }
+
+    fn group_delay(&self, f: T) -> T {
+        self.ba
+            .iter()
+            .fold(T::zero(), |t, ba| t + ba_group_delay(ba, f))
+    }
+}
+
+impl<T: iir_int::Sample, const S: u32> FrequencyResponse<f64> for iir_int::Biquad<T, S> {
+    fn response(&self, f: f64) -> Complex<f64> {
+        ba_response(&self.get_ba(), f)
+    }
+
+    fn group_delay(&self, f: f64) -> f64 {
+        ba_group_delay(&self.get_ba(), f)
+    }
+}
+
+impl<T: iir_int::Sample, const S: u32, const N: usize> FrequencyResponse<f64>
+    for iir_int::Cascade<T, S, N>
+{
+    fn response(&self, f: f64) -> Complex<f64> {
+        self.ba.iter().fold(Complex::new(1., 0.), |h, ba| {
+            h * ba_response(&iir_int::dequantize(ba, S), f)
+        })
+    }
+
+    fn group_delay(&self, f: f64) -> f64 {
+        self.ba.iter().fold(0., |t, ba| {
+            t + ba_group_delay(&iir_int::dequantize(ba, S), f)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iir::Shape;
+    use crate::testing::isclose;
+
+    #[test]
+    fn delay() {
+        // Pure delays
+        let mut i = IIR::<f64>::default();
+        for (k, ba) in [[1., 0., 0.], [0., 1., 0.], [0., 0., 1.]]
+            .iter()
+            .enumerate()
+        {
+            i.ba[..3].copy_from_slice(ba);
+            for f in [0., 0.1, 0.3].iter() {
+                let h = i.response(*f);
+                let h0 = Complex::from_polar(1., -2. * PI * f * k as f64);
+                assert!(isclose((h - h0).norm(), 0., 0., 1e-12));
+                assert!(isclose(i.group_delay(*f), k as f64, 0., 1e-12));
+            }
+        }
+        // First order lowpass y0 = a*y1 + (1 - a)*x0: tau(0) = a/(1 - a)
+        i.ba = [0.1, 0., 0., 0.9, 0.];
+        assert!(isclose(i.group_delay(0.), 9., 1e-12, 0.));
+        assert!(isclose(i.response(0.).norm(), 1., 1e-12, 0.));
+    }
+
+    #[test]
+    fn group_delay_numeric() {
+        let mut i = IIR::<f64>::default();
+        i.set_peaking(0.05, Shape::Q(3.), 5.).unwrap();
+        let df = 1e-6;
+        for f in [0.01, 0.04, 0.05, 0.2].iter() {
+            let dphi = (i.response(f + df) / i.response(f - df)).arg();
+            let tau = -dphi / (2. * PI * 2. * df);
+            assert!(isclose(i.group_delay(*f), tau, 1e-6, 1e-6));
+        }
+    }
+
+    #[test]
+    fn cascade() {
+        let mut i = [IIR::<f64>::default(); 2];
+        i[0].set_lowpass(0.1, Shape::Q(0.7), 1.).unwrap();
+        i[1].set_highpass(0.01, Shape::Q(0.5), 1.).unwrap();
+        let c = Cascade::<f64, 2> {
+            ba: [i[0].ba, i[1].ba],
+            ..Default::default()
+        };
+        let mut q = iir_int::Cascade::<i32, 30, 2>::default();
+        let mut qi = [iir_int::IIR::default(); 2];
+        for ((q, qi), i) in q.ba.iter_mut().zip(qi.iter_mut()).zip(i.iter()) {
+            qi.set_ba(&i.ba).unwrap();
+            *q = qi.ba;
+        }
+        let f = [0.001, 0.03, 0.1, 0.4];
+        let mut h = [Complex::new(0., 0.); 4];
+        c.sweep(&f, &mut h);
+        for (f, h) in f.iter().zip(h.iter()) {
+            let h0 = i[0].response(*f) * i[1].response(*f);
+            assert!(isclose((h - h0).norm(), 0., 0., 1e-12));
+            assert!(isclose((i[..].response(*f) - h0).norm(), 0., 0., 1e-12));
+            assert!(isclose((q.response(*f) - h0).norm(), 0., 0., 1e-6));
+            assert!(isclose(
+                (qi[0].response(*f) * qi[1].response(*f) - h0).norm(),
+                0.,
+                0.,
+                1e-6
+            ));
+            let tau = i[0].group_delay(*f) + i[1].group_delay(*f);
+            assert!(isclose(c.group_delay(*f), tau, 1e-12, 0.));
+            assert!(isclose(q.group_delay(*f), tau, 1e-4, 1e-4));
+        }
+    }
+}
@@ -0,0 +1,636 @@
+use super::{Shape, IIR};
+use crate::iir_int::{Biquad, Sample};
+use core::convert::TryFrom;
+use core::iter::Sum;
+use miniconf::{heapless, serde_json_core, DeserializeOwned, Error, Miniconf, MiniconfMetadata};
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+/// High level biquad filter description
+///
+/// A `FilterSpec` holds the intent (filter type and natural parameters) and computes
+/// the coefficients when applied to a filter. See the corresponding `IIR` designers
+/// for the meaning of the parameters.
+///
+/// # Miniconf
+///
+/// Atomic. The serialization is a flat object with the snake case filter type in the
+/// `type` field and the parameters of that type,
+/// e.g. `{"type": "lowpass", "f": 1e-3, "q": 0.707, "gain": 1.0}` or
+/// `{"type": "pi", "kp": -0.1, "ki": -1e-3, "g": 1e3}`.
+/// The shelf filters take one of `q`, `bandwidth`, or `slope`.
+/// Missing parameters and parameters not applicable to the type are rejected.
+///
+/// The internally tagged representation of serde (`#[serde(tag = "type")]`) requires
+/// buffering and is not available without `alloc`. The representation is implemented
+/// by converting from and to a flat struct with optional parameters instead.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    try_from = "Flat<T>",
+    into = "Flat<T>",
+    bound = "T: Copy + Serialize + DeserializeOwned"
+)]
+pub enum FilterSpec<T> {
+    /// Pure gain, see `IIR::new()`
+    Gain { gain: T },
+    /// See `IIR::set_lowpass()`
+    Lowpass { f: T, q: T, gain: T },
+    /// See `IIR::set_highpass()`
+    Highpass { f: T, q: T, gain: T },
+    /// See `IIR::set_bandpass()`
+    Bandpass { f: T, q: T, gain: T },
+    /// See `IIR::set_notch()`
+    Notch { f: T, q: T, gain: T },
+    /// See `IIR::set_allpass()`
+    Allpass { f: T, q: T, gain: T },
+    /// See `IIR::set_peaking()`
+    Peaking { f: T, q: T, gain: T },
+    /// See `IIR::set_lowshelf()`
+    Lowshelf { f: T, shape: Shape<T>, gain: T },
+    /// See `IIR::set_highshelf()`
+    Highshelf { f: T, shape: Shape<T>, gain: T },
+    /// See `IIR::set_notch_depth()`
+    NotchDepth { f: T, q: T, depth: T },
+    /// See `IIR::set_lead_lag()`
+    LeadLag { f: T, phase: T, gain: T },
+    /// See `IIR::set_pi()`
+    Pi { kp: T, ki: T, g: T },
+    /// See `IIR::set_pid()`
+    Pid { kp: T, ki: T, kd: T, gi: T, gd: T },
+    /// See `IIR::set_pii()`
+    Pii { kp: T, ki: T, kii: T, g: T },
+    /// See `IIR::set_ii()`
+    Ii { ki: T, kii: T, g: T },
+}
+
+/// Filter type of a `FilterSpec`
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Kind {
+    Gain,
+    Lowpass,
+    Highpass,
+    Bandpass,
+    Notch,
+    Allpass,
+    Peaking,
+    Lowshelf,
+    Highshelf,
+    NotchDepth,
+    LeadLag,
+    Pi,
+    Pid,
+    Pii,
+    Ii,
+}
+
+/// Flat serialization of a `FilterSpec`
+#[derive(Copy, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Flat<T> {
+    #[serde(rename = "type")]
+    kind: Option<Kind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    f: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    q: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    slope: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bandwidth: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    phase: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gain: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    depth: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kp: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ki: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kii: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kd: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    g: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gi: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gd: Option<T>,
+}
+
+impl<T> Flat<T> {
+    fn empty() -> Self {
+        Self {
+            kind: None,
+            f: None,
+            q: None,
+            slope: None,
+            bandwidth: None,
+            phase: None,
+            gain: None,
+            depth: None,
+            kp: None,
+            ki: None,
+            kii: None,
+            kd: None,
+            g: None,
+            gi: None,
+            gd: None,
+        }
+    }
+
+    fn set_shape(&mut self, shape: Shape<T>) {
+        match shape {
+            Shape::Q(q) => self.q = Some(q),
+            Shape::Bandwidth(bw) => self.bandwidth = Some(bw),
+            Shape::Slope(s) => self.slope = Some(s),
+        }
+    }
+
+    fn take_shape(&mut self) -> Result<Shape<T>, &'static str> {
+        match (self.q.take(), self.bandwidth.take(), self.slope.take()) {
+            (Some(q), None, None) => Ok(Shape::Q(q)),
+            (None, Some(bw), None) => Ok(Shape::Bandwidth(bw)),
+            (None, None, Some(s)) => Ok(Shape::Slope(s)),
+            _ => Err("shelf requires one of q, bandwidth, or slope"),
+        }
+    }
+}
+
+// Take a required parameter
+fn take<T>(p: &mut Option<T>) -> Result<T, &'static str> {
+    p.take().ok_or("missing parameter")
+}
+
+impl<T: Copy> TryFrom<Flat<T>> for FilterSpec<T> {
+    type Error = &'static str;
+
+    fn try_from(mut v: Flat<T>) -> Result<Self, Self::Error> {
+        let v = &mut v;
+        let spec = match v.kind.take().ok_or("missing type")? {
+            Kind::Gain => Self::Gain {
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Lowpass => Self::Lowpass {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Highpass => Self::Highpass {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Bandpass => Self::Bandpass {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Notch => Self::Notch {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Allpass => Self::Allpass {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Peaking => Self::Peaking {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Lowshelf => Self::Lowshelf {
+                f: take(&mut v.f)?,
+                shape: v.take_shape()?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Highshelf => Self::Highshelf {
+                f: take(&mut v.f)?,
+                shape: v.take_shape()?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::NotchDepth => Self::NotchDepth {
+                f: take(&mut v.f)?,
+                q: take(&mut v.q)?,
+                depth: take(&mut v.depth)?,
+            },
+            Kind::LeadLag => Self::LeadLag {
+                f: take(&mut v.f)?,
+                phase: take(&mut v.phase)?,
+                gain: take(&mut v.gain)?,
+            },
+            Kind::Pi => Self::Pi {
+                kp: take(&mut v.kp)?,
+                ki: take(&mut v.ki)?,
+                g: take(&mut v.g)?,
+            },
+            Kind::Pid => Self::Pid {
+                kp: take(&mut v.kp)?,
+                ki: take(&mut v.ki)?,
+                kd: take(&mut v.kd)?,
+                gi: take(&mut v.gi)?,
+                gd: take(&mut v.gd)?,
+            },
+            Kind::Pii => Self::Pii {
+                kp: take(&mut v.kp)?,
+                ki: take(&mut v.ki)?,
+                kii: take(&mut v.kii)?,
+                g: take(&mut v.g)?,
+            },
+            Kind::Ii => Self::Ii {
+                ki: take(&mut v.ki)?,
+                kii: take(&mut v.kii)?,
+                g: take(&mut v.g)?,
+            },
+        };
+        // All parameters of the type have been taken
+        let rest = [
+            v.f,
+            v.q,
+            v.slope,
+            v.bandwidth,
+            v.phase,
+            v.gain,
+            v.depth,
+            v.kp,
+            v.ki,
+            v.kii,
+            v.kd,
+            v.g,
+            v.gi,
+            v.gd,
+        ];
+        if rest.iter().any(Option::is_some) {
+            return Err("parameter not applicable");
+        }
+        Ok(spec)
+    }
+}
+
+impl<T: Copy> From<FilterSpec<T>> for Flat<T> {
+    fn from(spec: FilterSpec<T>) -> Self {
+        let mut v = Self::empty();
+        let kind = match spec {
+            FilterSpec::Gain { gain } => {
+                v.gain = Some(gain);
+                Kind::Gain
+            }
+            FilterSpec::Lowpass { f, q, gain } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.gain = Some(gain);
+                Kind::Lowpass
+            }
+            FilterSpec::Highpass { f, q, gain } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.gain = Some(gain);
+                Kind::Highpass
+            }
+            FilterSpec::Bandpass { f, q, gain } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.gain = Some(gain);
+                Kind::Bandpass
+            }
+            FilterSpec::Notch { f, q, gain } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.gain = Some(gain);
+                Kind::Notch
+            }
+            FilterSpec::Allpass { f, q, gain } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.gain = Some(gain);
+                Kind::Allpass
+            }
+            FilterSpec::Peaking { f, q, gain } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.gain = Some(gain);
+                Kind::Peaking
+            }
+            FilterSpec::Lowshelf { f, shape, gain } => {
+                v.f = Some(f);
+                v.set_shape(shape);
+                v.gain = Some(gain);
+                Kind::Lowshelf
+            }
+            FilterSpec::Highshelf { f, shape, gain } => {
+                v.f = Some(f);
+                v.set_shape(shape);
+                v.gain = Some(gain);
+                Kind::Highshelf
+            }
+            FilterSpec::NotchDepth { f, q, depth } => {
+                v.f = Some(f);
+                v.q = Some(q);
+                v.depth = Some(depth);
+                Kind::NotchDepth
+            }
+            FilterSpec::LeadLag { f, phase, gain } => {
+                v.f = Some(f);
+                v.phase = Some(phase);
+                v.gain = Some(gain);
+                Kind::LeadLag
+            }
+            FilterSpec::Pi { kp, ki, g } => {
+                v.kp = Some(kp);
+                v.ki = Some(ki);
+                v.g = Some(g);
+                Kind::Pi
+            }
+            FilterSpec::Pid { kp, ki, kd, gi, gd } => {
+                v.kp = Some(kp);
+                v.ki = Some(ki);
+                v.kd = Some(kd);
+                v.gi = Some(gi);
+                v.gd = Some(gd);
+                Kind::Pid
+            }
+            FilterSpec::Pii { kp, ki, kii, g } => {
+                v.kp = Some(kp);
+                v.ki = Some(ki);
+                v.kii = Some(kii);
+                v.g = Some(g);
+                Kind::Pii
+            }
+            FilterSpec::Ii { ki, kii, g } => {
+                v.ki = Some(ki);
+                v.kii = Some(kii);
+                v.g = Some(g);
+                Kind::Ii
+            }
+        };
+        v.kind = Some(kind);
+        v
+    }
+}
+
+impl<T: Float + Default + Sum<T>> FilterSpec<T> {
+    /// Compute the coefficients.
+    ///
+    /// # Arguments
+    /// * `iir` - Filter to compute the coefficients for. Only `ba` is modified.
+    fn design(&self, iir: &mut IIR<T>) -> Result<(), &'static str> {
+        match *self {
+            Self::Gain { gain } => {
+                iir.ba = IIR::new(gain, T::zero(), T::zero()).ba;
+                Ok(())
+            }
+            Self::Lowpass { f, q, gain } => iir.set_lowpass(f, Shape::Q(q), gain),
+            Self::Highpass { f, q, gain } => iir.set_highpass(f, Shape::Q(q), gain),
+            Self::Bandpass { f, q, gain } => iir.set_bandpass(f, Shape::Q(q), gain),
+            Self::Notch { f, q, gain } => iir.set_notch(f, Shape::Q(q), gain),
+            Self::Allpass { f, q, gain } => iir.set_allpass(f, Shape::Q(q), gain),
+            Self::Peaking { f, q, gain } => iir.set_peaking(f, Shape::Q(q), gain),
+            Self::Lowshelf { f, shape, gain } => iir.set_lowshelf(f, shape, gain),
+            Self::Highshelf { f, shape, gain } => iir.set_highshelf(f, shape, gain),
+            Self::NotchDepth { f, q, depth } => iir.set_notch_depth(f, Shape::Q(q), depth),
+            Self::LeadLag { f, phase, gain } => iir.set_lead_lag(f, phase, gain),
+            Self::Pi { kp, ki, g } => iir.set_pi(kp, ki, g),
+            Self::Pid { kp, ki, kd, gi, gd } => iir.set_pid(kp, ki, kd, gi, gd),
+            Self::Pii { kp, ki, kii, g } => iir.set_pii(kp, ki, kii, g),
+            Self::Ii { ki, kii, g } => iir.set_ii(ki, kii, g),
+        }
+    }
+
+    /// Compute, validate and apply the coefficients.
+    ///
+    /// `iir` is only modified if the design succeeds and the result passes
+    /// `IIR::validate()`.
+    ///
+    /// # Arguments
+    /// * `iir` - Filter to apply the specification to. Only `ba` is modified.
+    pub fn apply(&self, iir: &mut IIR<T>) -> Result<(), &'static str> {
+        let mut new = *iir;
+        self.design(&mut new)?;
+        new.validate().map_err(|e| e.as_str())?;
+        *iir = new;
+        Ok(())
+    }
+}
+
+impl FilterSpec<f64> {
+    /// Compute, quantize, validate and apply the coefficients to an integer biquad.
+    ///
+    /// `iir` is only modified if the design and quantization succeed and the result
+    /// passes `Biquad::validate()`.
+    ///
+    /// # Arguments
+    /// * `iir` - Filter to apply the specification to. Only `ba` is modified.
+    pub fn apply_int<T: Sample, const S: u32>(
+        &self,
+        iir: &mut Biquad<T, S>,
+    ) -> Result<(), &'static str> {
+        let mut design = IIR::default();
+        self.design(&mut design)?;
+        let mut new = *iir;
+        new.set_ba(&design.ba)?;
+        new.validate().map_err(|e| e.as_str())?;
+        *iir = new;
+        Ok(())
+    }
+}
+
+impl<T: Copy + Serialize + DeserializeOwned> Miniconf for FilterSpec<T> {
+    fn string_set(
+        &mut self,
+        mut topic_parts: core::iter::Peekable<core::str::Split<char>>,
+        value: &[u8],
+    ) -> Result<(), Error> {
+        if topic_parts.peek().is_some() {
+            return Err(Error::AtomicUpdateRequired);
+        }
+        *self = serde_json_core::from_slice(value)?.0;
+        Ok(())
+    }
+
+    fn string_get(
+        &self,
+        mut topic_parts: core::iter::Peekable<core::str::Split<char>>,
+        value: &mut [u8],
+    ) -> Result<usize, Error> {
+        if topic_parts.peek().is_some() {
+            return Err(Error::AtomicUpdateRequired);
+        }
+        serde_json_core::to_slice(self, value).map_err(|_| Error::SerializationFailed)
+    }
+
+    fn get_metadata(&self) -> MiniconfMetadata {
+        MiniconfMetadata {
+            max_topic_size: 0,
+            max_depth: 1,
+        }
+    }
+
+    fn recurse_paths<const TS: usize>(
+        &self,
+        index: &mut [usize],
+        _topic: &mut heapless::String<TS>,
+    ) -> Option<()> {
+        let i = index[0];
+        index[0] += 1;
+        if i == 0 {
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iir::Invalid;
+    use crate::iir_int;
+
+    #[derive(Copy, Clone, Debug, Miniconf)]
+    struct Settings {
+        spec: FilterSpec<f64>,
+        gain: f64,
+    }
+
+    #[test]
+    fn miniconf() {
+        let mut s = Settings {
+            spec: FilterSpec::Gain { gain: 1. },
+            gain: 0.,
+        };
+        s.set(
+            "spec",
+            br#"{"type": "lowpass", "f": 1e-3, "q": 0.707, "gain": 1}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            s.spec,
+            FilterSpec::Lowpass {
+                f: 1e-3,
+                q: 0.707,
+                gain: 1.
+            }
+        );
+        s.set(
+            "spec",
+            br#"{"type": "pi", "kp": -0.1, "ki": -1e-3, "g": 1e3}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            s.spec,
+            FilterSpec::Pi {
+                kp: -0.1,
+                ki: -1e-3,
+                g: 1e3
+            }
+        );
+        s.set(
+            "spec",
+            br#"{"type": "lead_lag", "f": 0.01, "phase": 0.5, "gain": 2}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            s.spec,
+            FilterSpec::LeadLag {
+                f: 0.01,
+                phase: 0.5,
+                gain: 2.
+            }
+        );
+        assert!(s.set("spec/pi", b"{}").is_err());
+        s.set(
+            "spec",
+            br#"{"type": "lowshelf", "f": 0.1, "slope": 1, "gain": 4}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            s.spec,
+            FilterSpec::Lowshelf {
+                f: 0.1,
+                shape: Shape::Slope(1.),
+                gain: 4.
+            }
+        );
+        // Missing parameter
+        assert!(s.set("spec", br#"{"type": "lowpass", "f": 1e-3}"#).is_err());
+        // Parameter not applicable
+        assert!(s
+            .set(
+                "spec",
+                br#"{"type": "pi", "kp": 1, "ki": 1, "g": 1, "q": 1}"#
+            )
+            .is_err());
+        // Ambiguous shelf shape
+        assert!(s
+            .set(
+                "spec",
+                br#"{"type": "highshelf", "f": 0.1, "q": 1, "slope": 1, "gain": 4}"#
+            )
+            .is_err());
+        // Missing type, unknown parameter
+        assert!(s.set("spec", br#"{"gain": 1}"#).is_err());
+        assert!(s
+            .set("spec", br#"{"type": "gain", "gain": 1, "x": 1}"#)
+            .is_err());
+        s.set(
+            "spec",
+            br#"{"type": "lead_lag", "f": 0.01, "phase": 0.5, "gain": 2}"#,
+        )
+        .unwrap();
+        let mut buf = [0; 64];
+        let len = s.get("spec", &mut buf).unwrap();
+        assert_eq!(
+            &buf[..len],
+            br#"{"type":"lead_lag","f":0.01,"phase":0.5,"gain":2.0}"#
+        );
+    }
+
+    #[test]
+    fn apply() {
+        let mut i = IIR::new(1., -1., 1.);
+        let spec = FilterSpec::Lowpass {
+            f: 0.1,
+            q: 0.707,
+            gain: 2.,
+        };
+        spec.apply(&mut i).unwrap();
+        let mut j = IIR::new(1., -1., 1.);
+        j.set_lowpass(0.1, Shape::Q(0.707), 2.).unwrap();
+        assert_eq!(i.ba, j.ba);
+        assert_eq!((i.y_min, i.y_max), (-1., 1.));
+
+        // Design errors
+        let ba = i.ba;
+        assert!(FilterSpec::Lowpass {
+            f: 0.6,
+            q: 0.707,
+            gain: 1.
+        }
+        .apply(&mut i)
+        .is_err());
+        assert_eq!(i.ba, ba);
+        // Validation errors
+        i.y_min = 2.;
+        assert_eq!(
+            FilterSpec::Gain { gain: 1. }.apply(&mut i),
+            Err(Invalid::Limits.as_str())
+        );
+        assert_eq!(i.ba, ba);
+
+        let mut q = iir_int::IIR {
+            y_min: -1000,
+            y_max: 1000,
+            ..Default::default()
+        };
+        spec.apply_int(&mut q).unwrap();
+        let mut r = iir_int::IIR::default();
+        r.set_lowpass(0.1, Shape::Q(0.707), 2.).unwrap();
+        assert_eq!(q.ba, r.ba);
+        assert!(FilterSpec::Gain { gain: 3. }.apply_int(&mut q).is_err());
+        assert_eq!(q.ba, r.ba);
+    }
+}
@@ -0,0 +1,119 @@
+use super::{macc, IIR};
+use miniconf::Miniconf;
+use num_traits::Float;
+
+/// Discrete time state-space system.
+///
+/// `N` states, `I` inputs, and `O` outputs. The update computes the output
+/// `y = C x + D u` from the current state `x` and input `u` and then advances
+/// the state to `A x + B u`.
+///
+/// The matrices are stored row-major: `a[i][j]` is the contribution of state `j`
+/// to the next value of state `i`, `b[i][k]` that of input `k` to state `i`,
+/// `c[o][j]` that of state `j` to output `o` and `d[o][k]` that of input `k`
+/// to output `o`.
+///
+/// # Miniconf
+///
+/// `{"a": [[a00, ..], ..], "b": [[b00, ..], ..], "c": [[c00, ..], ..], "d": [[d00, ..], ..]}`
+///
+/// The elements are accessible individually (e.g. `c/1/0`).
+#[derive(Copy, Clone, Debug, Miniconf)]
+pub struct StateSpace<T, const N: usize, const I: usize, const O: usize> {
+    pub a: [[T; N]; N],
+    pub b: [[T; I]; N],
+    pub c: [[T; N]; O],
+    pub d: [[T; I]; O],
+}
+
+impl<T: Default + Copy, const N: usize, const I: usize, const O: usize> Default
+    for StateSpace<T, N, I, O>
+{
+    fn default() -> Self {
+        Self {
+            a: [[T::default(); N]; N],
+            b: [[T::default(); I]; N],
+            c: [[T::default(); N]; O],
+            d: [[T::default(); I]; O],
+        }
+    }
+}
+
+impl<T: Float, const N: usize, const I: usize, const O: usize> StateSpace<T, N, I, O> {
+    /// Feed new inputs into the system, update the state, and
+    /// return the new outputs. Only the state `x` is modified.
+    ///
+    /// # Arguments
+    /// * `x` - Current state.
+    /// * `u` - New inputs.
+    pub fn update(&self, x: &mut [T; N], u: &[T; I]) -> [T; O] {
+        let mut y = [T::zero(); O];
+        for (y, (c, d)) in y.iter_mut().zip(self.c.iter().zip(self.d.iter())) {
+            *y = macc(macc(T::zero(), x, c), u, d);
+        }
+        let mut x1 = [T::zero(); N];
+        for (x1, (a, b)) in x1.iter_mut().zip(self.a.iter().zip(self.b.iter())) {
+            *x1 = macc(macc(T::zero(), x, a), u, b);
+        }
+        *x = x1;
+        y
+    }
+}
+
+impl<T: Float> From<IIR<T>> for StateSpace<T, 2, 1, 1> {
+    /// Controllable canonical form of the biquad.
+    ///
+    /// The output offset and limits are not represented.
+    fn from(iir: IIR<T>) -> Self {
+        let ba = iir.ba;
+        Self {
+            a: [[ba[3], ba[4]], [T::one(), T::zero()]],
+            b: [[T::one()], [T::zero()]],
+            c: [[ba[1] + ba[0] * ba[3], ba[2] + ba[0] * ba[4]]],
+            d: [[ba[0]]],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iir::Shape;
+    use crate::testing::isclose;
+    use miniconf::Miniconf;
+
+    #[test]
+    fn biquad() {
+        let mut i = IIR::new(1., f64::NEG_INFINITY, f64::INFINITY);
+        i.set_peaking(0.02, Shape::Q(2.), 3.).unwrap();
+        let s = StateSpace::from(i);
+        let (mut xy, mut x) = ([0.; 5], [0.; 2]);
+        for k in 0..500 {
+            let u = ((k * 7) % 11) as f64 - 5.;
+            let y = i.update(&mut xy, u, false);
+            assert!(isclose(s.update(&mut x, &[u])[0], y, 1e-12, 1e-12));
+        }
+    }
+
+    #[test]
+    fn mimo() {
+        // Two first order lowpasses with cross-coupling from the first to the second
+        let mut s = StateSpace::<f32, 2, 2, 2>::default();
+        s.set("a/0/0", b"0.5").unwrap();
+        s.set("a/1/1", b"0.75").unwrap();
+        s.set("b/0/0", b"0.5").unwrap();
+        s.set("b/1/0", b"0.1").unwrap();
+        s.set("b/1/1", b"0.25").unwrap();
+        s.set("c/0/0", b"1").unwrap();
+        s.set("c/1/1", b"1").unwrap();
+        let mut x = [0.; 2];
+        let mut y = [0.; 2];
+        for _ in 0..200 {
+            y = s.update(&mut x, &[1., 2.]);
+        }
+        assert!((y[0] - 1.).abs() < 1e-6);
+        assert!((y[1] - 2.4).abs() < 1e-5);
+        s.set("d/1/0", b"-2.4").unwrap();
+        assert!(s.update(&mut x, &[1., 2.])[1].abs() < 1e-3);
+    }
+}
@@ -1,43 +1,54 @@
+use super::iir::{self, Clip, Invalid, Shape};
 use super::tools::macc_i32;
-use core::f64::consts::PI;
-use miniconf::MiniconfAtomic;
+use super::{Complex, Slew, SlewSample};
+use core::convert::TryFrom;
+use miniconf::{Miniconf, MiniconfAtomic};
+use num_traits::{Float, NumCast, PrimInt};
 use serde::{Deserialize, Serialize};
 
+mod iirn;
+mod shaped;
+mod state_space;
+pub use iirn::*;
+pub use shaped::*;
+pub use state_space::*;
+
 /// Generic vector for integer IIR filter.
 /// This struct is used to hold the x/y input/output data vector or the b/a coefficient
 /// vector.
 pub type Vec5 = [i32; 5];
 
-trait Coeff {
-    /// Lowpass biquad filter using cutoff and sampling frequencies.  Taken from:
-    /// https://webaudio.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html
-    ///
-    /// # Args
-    /// * `f` - Corner frequency, or 3dB cutoff frequency (in units of sample rate).
-    ///         This is only accurate for low corner frequencies less than ~0.01.
-    /// * `q` - Quality factor (1/sqrt(2) for critical).
-    /// * `k` - DC gain.
-    ///
-    /// # Returns
-    /// 2nd-order IIR filter coefficients in the form [b0,b1,b2,a1,a2]. a0 is set to -1.
-    fn lowpass(f: f64, q: f64, k: f64) -> Self;
+/// Integer sample and coefficient type of a `Biquad`.
+pub trait Sample: PrimInt + Default {
+    /// Multiply-accumulate with output offset, rounding bias (half up) and shift
+    /// using a wider accumulator, see `macc_i32()`. The accumulator and the output
+    /// saturate.
+    fn macc(y0: Self, x: &[Self], a: &[Self], shift: u32) -> Self;
+}
+
+macro_rules! impl_sample {
+    ($t:ty, $a:ty) => {
+        impl Sample for $t {
+            fn macc(y0: Self, x: &[Self], a: &[Self], shift: u32) -> Self {
+                // Rounding bias, half up
+                let y0 = ((y0 as $a) << shift) + (1 << (shift - 1));
+                let y = x
+                    .iter()
+                    .zip(a)
+                    .map(|(x, a)| *x as $a * *a as $a)
+                    .fold(y0, |y, xa| y.saturating_add(xa));
+                (y >> shift).max(<$t>::MIN as $a).min(<$t>::MAX as $a) as $t
+            }
+        }
+    };
 }
 
-impl Coeff for Vec5 {
-    fn lowpass(f: f64, q: f64, k: f64) -> Self {
-        // 3rd order Taylor approximation of sin and cos.
-        let f = f * 2. * PI;
-        let f2 = f * f * 0.5;
-        let fcos = 1. - f2;
-        let fsin = f * (1. - f2 / 3.);
-        let alpha = fsin / (2. * q);
-        // IIR uses Q2.30 fixed point
-        let a0 = (1. + alpha) / (1 << IIR::SHIFT) as f64;
-        let b0 = (k / 2. * (1. - fcos) / a0 + 0.5) as _;
-        let a1 = (2. * fcos / a0 + 0.5) as _;
-        let a2 = ((alpha - 1.) / a0 + 0.5) as _;
+impl_sample!(i16, i32);
+impl_sample!(i64, i128);
 
-        [b0, 2 * b0, b0, a1, a2]
+impl Sample for i32 {
+    fn macc(y0: Self, x: &[Self], a: &[Self], shift: u32) -> Self {
+        macc_i32(y0, x, a, shift)
     }
 }
 
@@ -46,18 +57,242 @@ impl Coeff for Vec5 {
 /// See `dsp::iir::IIR` for general implementation details.
 /// Offset and limiting disabled to suit lowpass applications.
 /// Coefficient scaling fixed and optimized.
+///
+/// The biquad is generic over the sample and coefficient type `T` (`i16`, `i32`, `i64`)
+/// and the number of fractional coefficient bits `S` (`1 <= S < T::BITS`).
+/// Products are accumulated in the next wider integer type.
+/// `IIR` is the `i32` biquad with Q2.30 coefficients.
 #[derive(Copy, Clone, Default, Debug, MiniconfAtomic, Serialize, Deserialize)]
-pub struct IIR {
-    pub ba: Vec5,
-    pub y_offset: i32,
-    pub y_min: i32,
-    pub y_max: i32,
+pub struct Biquad<T, const S: u32> {
+    pub ba: [T; 5],
+    pub y_offset: T,
+    pub y_min: T,
+    pub y_max: T,
 }
 
-impl IIR {
-    /// Coefficient fixed point format: signed Q2.30.
-    /// Tailored to low-passes, PI, II etc.
-    pub const SHIFT: u32 = 30;
+/// Integer biquad IIR with `i32` samples and Q2.30 coefficients.
+pub type IIR = Biquad<i32, 30>;
+
+impl<T: Sample, const S: u32> Biquad<T, S> {
+    /// Coefficient fixed point format: `S` fractional bits.
+    /// Signed Q2.30 for `IIR`, tailored to low-passes, PI, II etc.
+    pub const SHIFT: u32 = S;
+
+    /// Quantize and set floating point filter coefficients.
+    ///
+    /// The coefficients are rounded to the nearest fixed point value (half away from zero).
+    /// `ba` is only modified if all coefficients are in range.
+    ///
+    /// # Arguments
+    /// * `ba` - Coefficients `[b0, b1, b2, -a1, -a2]` normalized to `a0 = 1`, see `iir::Vec5`.
+    pub fn set_ba(&mut self, ba: &iir::Vec5<f64>) -> Result<(), &'static str> {
+        self.ba = quantize(ba, S)?;
+        Ok(())
+    }
+
+    /// Convert a floating point biquad.
+    ///
+    /// The coefficients are quantized using `set_ba()`. The output offset and
+    /// limits are scaled such that `full_scale` corresponds to the integer full scale
+    /// `2^(T::BITS - 1)` and rounded. The limits saturate at the integer range.
+    ///
+    /// # Arguments
+    /// * `iir` - Floating point biquad.
+    /// * `full_scale` - Floating point full scale.
+    ///
+    /// # Returns
+    /// The integer biquad and the quantization effects on its coefficients.
+    pub fn from_float(
+        iir: &iir::IIR<f64>,
+        full_scale: f64,
+    ) -> Result<(Self, Quantization), &'static str> {
+        let scale = int_full_scale::<T>() / full_scale;
+        let limit = |y: f64| -> Result<T, &'static str> {
+            let y = Float::round(y * scale);
+            if y.is_nan() {
+                Err("invalid limit")
+            } else {
+                Ok(saturate(y))
+            }
+        };
+        let mut q = Self {
+            ba: [T::zero(); 5],
+            y_offset: <T as NumCast>::from(Float::round(iir.y_offset * scale))
+                .ok_or("offset out of range")?,
+            y_min: limit(iir.y_min)?,
+            y_max: limit(iir.y_max)?,
+        };
+        q.set_ba(&iir.ba)?;
+        let quantization = Quantization::new(&iir.ba, &q.get_ba());
+        Ok((q, quantization))
+    }
+
+    /// Convert to a floating point biquad.
+    ///
+    /// See `from_float()` for the scaling.
+    ///
+    /// # Arguments
+    /// * `full_scale` - Floating point full scale.
+    pub fn to_float(&self, full_scale: f64) -> iir::IIR<f64> {
+        let scale = full_scale / int_full_scale::<T>();
+        iir::IIR {
+            ba: self.get_ba(),
+            y_offset: self.y_offset.to_f64().unwrap() * scale,
+            y_min: self.y_min.to_f64().unwrap() * scale,
+            y_max: self.y_max.to_f64().unwrap() * scale,
+        }
+    }
+
+    /// Get the floating point equivalent of the quantized coefficients.
+    pub fn get_ba(&self) -> iir::Vec5<f64> {
+        dequantize(&self.ba, S)
+    }
+
+    /// Ramp the output offset toward a target with limited rate and acceleration.
+    ///
+    /// See `iir::IIR::slew_y_offset()`.
+    pub fn slew_y_offset(&mut self, slew: &Slew<T>, v: &mut T, y_offset: T)
+    where
+        T: SlewSample,
+    {
+        let mut yv = [self.y_offset, *v];
+        self.y_offset = slew.update(&mut yv, y_offset);
+        *v = yv[1];
+    }
+
+    /// Configures IIR filter coefficients for second order lowpass behavior.
+    ///
+    /// The designers compute exact coefficients (see `iir::IIR::set_lowpass()` and
+    /// the other `iir::IIR` designers) valid over the entire band and quantize them
+    /// using `set_ba()`. They only modify `ba`.
+    ///
+    /// # Arguments
+    /// * `f` - Corner frequency (in units of sample rate, `0 < f < 0.5`).
+    /// * `shape` - Filter shape, typically `Shape::Q(1/sqrt(2))`.
+    /// * `k` - DC gain.
+    pub fn set_lowpass(&mut self, f: f64, shape: Shape<f64>, k: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_lowpass(f, shape, k)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for second order highpass behavior.
+    ///
+    /// See `iir::IIR::set_highpass()`.
+    pub fn set_highpass(&mut self, f: f64, shape: Shape<f64>, k: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_highpass(f, shape, k)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for bandpass behavior.
+    ///
+    /// See `iir::IIR::set_bandpass()`.
+    pub fn set_bandpass(&mut self, f: f64, shape: Shape<f64>, k: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_bandpass(f, shape, k)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for notch behavior.
+    ///
+    /// See `iir::IIR::set_notch()`.
+    pub fn set_notch(&mut self, f: f64, shape: Shape<f64>, k: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_notch(f, shape, k)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for allpass behavior.
+    ///
+    /// See `iir::IIR::set_allpass()`.
+    pub fn set_allpass(&mut self, f: f64, shape: Shape<f64>, k: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_allpass(f, shape, k)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for peaking equalizer behavior.
+    ///
+    /// See `iir::IIR::set_peaking()`.
+    pub fn set_peaking(&mut self, f: f64, shape: Shape<f64>, g: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_peaking(f, shape, g)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for low shelf behavior.
+    ///
+    /// See `iir::IIR::set_lowshelf()`.
+    pub fn set_lowshelf(&mut self, f: f64, shape: Shape<f64>, g: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_lowshelf(f, shape, g)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Configures IIR filter coefficients for high shelf behavior.
+    ///
+    /// See `iir::IIR::set_highshelf()`.
+    pub fn set_highshelf(&mut self, f: f64, shape: Shape<f64>, g: f64) -> Result<(), &'static str> {
+        let mut iir = iir::IIR::default();
+        iir.set_highshelf(f, shape, g)?;
+        self.set_ba(&iir.ba)
+    }
+
+    /// Compute the poles of the transfer function, see `iir::IIR::poles()`.
+    pub fn poles(&self) -> [Complex<f64>; 2] {
+        self.as_float().poles()
+    }
+
+    /// Compute the zeros of the transfer function, see `iir::IIR::zeros()`.
+    pub fn zeros(&self) -> [Complex<f64>; 2] {
+        self.as_float().zeros()
+    }
+
+    fn as_float(&self) -> iir::IIR<f64> {
+        iir::IIR {
+            ba: self.get_ba(),
+            y_offset: self.y_offset.to_f64().unwrap(),
+            y_min: self.y_min.to_f64().unwrap(),
+            y_max: self.y_max.to_f64().unwrap(),
+        }
+    }
+
+    /// Compute a steady state for a given output, see `iir::IIR::state_for_output()`.
+    ///
+    /// The input is rounded. The output is consistent to within the rounding error.
+    pub fn state_for_output(&self, y: T) -> Result<[T; 5], &'static str> {
+        let xy = self.as_float().state_for_output(y.to_f64().unwrap())?;
+        let x = <T as NumCast>::from(Float::round(xy[0])).ok_or("input out of range")?;
+        Ok([x, x, y, y, y])
+    }
+
+    /// Compute the steady state for a given input, see `iir::IIR::state_for_input()`.
+    ///
+    /// The output is rounded and saturates at the integer range.
+    pub fn state_for_input(&self, x: T) -> Result<[T; 5], &'static str> {
+        let xy = self.as_float().state_for_input(x.to_f64().unwrap())?;
+        // Already limited but `T::max_value()` may round up in `f64`
+        let y = saturate(Float::round(xy[2]));
+        Ok([x, x, y, y, y])
+    }
+
+    /// Check the filter configuration, see `iir::IIR::validate()`.
+    ///
+    /// The stability check is exact. All integer coefficients are within the fixed
+    /// point range; out of range coefficients are already rejected by `set_ba()` and
+    /// the designers.
+    pub fn validate(&self) -> Result<(), Invalid> {
+        let one = 1i128 << S;
+        let (a1, a2) = (self.ba[3].to_i128().unwrap(), self.ba[4].to_i128().unwrap());
+        if a2.abs() > one || a1.abs() > one - a2 {
+            return Err(Invalid::Unstable);
+        }
+        if self.y_min > self.y_max {
+            return Err(Invalid::Limits);
+        }
+        Ok(())
+    }
 
     /// Feed a new input value into the filter, update the filter state, and
     /// return the new output. Only the state `xy` is modified.
@@ -65,7 +300,19 @@ impl IIR {
     /// # Arguments
     /// * `xy` - Current filter state.
     /// * `x0` - New input.
-    pub fn update(&self, xy: &mut Vec5, x0: i32) -> i32 {
+    /// * `hold` - Hold the output, see `iir::IIR::update()`.
+    pub fn update(&self, xy: &mut [T; 5], x0: T, hold: bool) -> T {
+        self.update_clip(xy, x0, hold).0
+    }
+
+    /// Like `update()` but also report whether the output was limited,
+    /// see `iir::IIR::update_clip()`.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the output.
+    pub fn update_clip(&self, xy: &mut [T; 5], x0: T, hold: bool) -> (T, Option<Clip>) {
         let n = self.ba.len();
         debug_assert!(xy.len() == n);
         // `xy` contains       x0 x1 y0 y1 y2
@@ -76,22 +323,597 @@ impl IIR {
         // Store x0            x0 x1 x2 y1 y2
         xy[0] = x0;
         // Compute y0 by multiply-accumulate
-        let y0 = macc_i32(self.y_offset, xy, &self.ba, IIR::SHIFT);
+        let y0 = if hold {
+            xy[n / 2 + 1]
+        } else {
+            T::macc(self.y_offset, xy, &self.ba, S)
+        };
         // Limit y0
-        let y0 = y0.max(self.y_min).min(self.y_max);
+        let y = y0.max(self.y_min).min(self.y_max);
+        let clip = if y0 > y {
+            Some(Clip::Max)
+        } else if y0 < y {
+            Some(Clip::Min)
+        } else {
+            None
+        };
+        let y0 = y;
         // Store y0            x0 x1 y0 y1 y2
         xy[n / 2] = y0;
+        (y0, clip)
+    }
+
+    /// Filter a block of samples in place, see `iir::IIR::update_block()`.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x` - Input samples, replaced by the output samples.
+    /// * `hold` - Hold the output.
+    pub fn update_block(&self, xy: &mut [T; 5], x: &mut [T], hold: bool) {
+        for x in x.iter_mut() {
+            *x = self.update(xy, *x, hold);
+        }
+    }
+
+    /// Filter a block of samples, see `iir::IIR::update_block_to()`.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x` - Input samples.
+    /// * `y` - Output samples. Must be the same length as `x`.
+    /// * `hold` - Hold the output.
+    pub fn update_block_to(&self, xy: &mut [T; 5], x: &[T], y: &mut [T], hold: bool) {
+        debug_assert_eq!(x.len(), y.len());
+        for (x, y) in x.iter().zip(y.iter_mut()) {
+            *y = self.update(xy, *x, hold);
+        }
+    }
+
+    /// Filter a block of channel-interleaved samples in place,
+    /// see `iir::IIR::update_interleaved()`.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter states, one per channel.
+    /// * `x` - Interleaved input samples, replaced by the output samples.
+    ///   The length must be a multiple of `C`.
+    /// * `hold` - Hold the output.
+    pub fn update_interleaved<const C: usize>(
+        &self,
+        xy: &mut [[T; 5]; C],
+        x: &mut [T],
+        hold: bool,
+    ) {
+        debug_assert_eq!(x.len() % C, 0);
+        // Coefficients, offset, and limits in locals
+        let ba = self.ba;
+        let (y_offset, y_min, y_max) = (self.y_offset, self.y_min, self.y_max);
+        // Transpose the states such that the channels are contiguous for each element
+        let mut s = iir::transpose(xy);
+        let [x1, x2, y1, y2, y3] = &mut s;
+        for x in x.chunks_exact_mut(C) {
+            for (c, x) in x.iter_mut().enumerate() {
+                // See `update_clip()`
+                let x0 = *x;
+                let y0 = if hold {
+                    y1[c]
+                } else {
+                    T::macc(y_offset, &[x0, x1[c], x2[c], y1[c], y2[c]], &ba, S)
+                };
+                let y0 = y0.max(y_min).min(y_max);
+                x2[c] = x1[c];
+                x1[c] = x0;
+                y3[c] = y2[c];
+                y2[c] = y1[c];
+                y1[c] = y0;
+                *x = y0;
+            }
+        }
+        *xy = iir::transpose(&s);
+    }
+
+    /// Track an externally forced output, see `iir::IIR::track()`.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `y0` - Forced output.
+    pub fn track(&self, xy: &mut [T; 5], x0: T, y0: T) -> T {
+        // See `update()`
+        xy.copy_within(0..4, 1);
+        xy[0] = x0;
+        let y0 = y0.max(self.y_min).min(self.y_max);
+        xy[2] = y0;
+        y0
+    }
+}
+
+/// Quantization effects of a floating point to integer biquad conversion.
+///
+/// See `Biquad::from_float()`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quantization {
+    /// Displacement (distance in the z-plane) of the quantized poles.
+    pub poles: [f64; 2],
+    /// Displacement of the quantized zeros.
+    /// Zero if both the original and the quantized zero are at infinity.
+    pub zeros: [f64; 2],
+    /// Relative DC gain error of the quantized filter.
+    /// NaN or infinite for filters with poles at DC.
+    pub dc_gain: f64,
+}
+
+impl Quantization {
+    fn new(ba: &iir::Vec5<f64>, quantized: &iir::Vec5<f64>) -> Self {
+        let (f, q) = (
+            iir::IIR {
+                ba: *ba,
+                ..Default::default()
+            },
+            iir::IIR {
+                ba: *quantized,
+                ..Default::default()
+            },
+        );
+        let k = |ba: &iir::Vec5<f64>| (ba[0] + ba[1] + ba[2]) / (1. - ba[3] - ba[4]);
+        Self {
+            poles: displacement(f.poles(), q.poles()),
+            zeros: displacement(f.zeros(), q.zeros()),
+            dc_gain: k(quantized) / k(ba) - 1.,
+        }
+    }
+}
+
+/// Convert to integer, saturating at the integer range.
+fn saturate<T: Sample>(y: f64) -> T {
+    <T as NumCast>::from(y).unwrap_or(if y > 0. {
+        T::max_value()
+    } else {
+        T::min_value()
+    })
+}
+
+/// Integer full scale `2^(T::BITS - 1)`
+fn int_full_scale<T: Sample>() -> f64 {
+    // `T::zero().count_zeros()` is `T::BITS`
+    Float::powi(2f64, T::zero().count_zeros() as i32 - 1)
+}
+
+/// Distances between the roots in `a` and `b` for the closer pairing.
+fn displacement(a: [Complex<f64>; 2], b: [Complex<f64>; 2]) -> [f64; 2] {
+    let d = |a: Complex<f64>, b: Complex<f64>| {
+        if !a.is_finite() && !b.is_finite() {
+            0.
+        } else {
+            (a - b).norm()
+        }
+    };
+    let direct = [d(a[0], b[0]), d(a[1], b[1])];
+    let swapped = [d(a[0], b[1]), d(a[1], b[0])];
+    if direct[0].max(direct[1]) <= swapped[0].max(swapped[1]) {
+        direct
+    } else {
+        swapped
+    }
+}
+
+impl<T: Sample, const S: u32> From<Biquad<T, S>> for iir::IIR<f64> {
+    /// Convert to floating point with unchanged units (full scale `2^(T::BITS - 1)`).
+    fn from(value: Biquad<T, S>) -> Self {
+        value.to_float(int_full_scale::<T>())
+    }
+}
+
+impl<T: Sample, const S: u32> TryFrom<iir::IIR<f64>> for Biquad<T, S> {
+    type Error = &'static str;
+
+    /// Convert from floating point with unchanged units (full scale `2^(T::BITS - 1)`).
+    fn try_from(value: iir::IIR<f64>) -> Result<Self, Self::Error> {
+        Self::from_float(&value, int_full_scale::<T>()).map(|(iir, _)| iir)
+    }
+}
+
+/// Quantize floating point coefficients to fixed point with `shift` fractional bits.
+///
+/// The coefficients are rounded to the nearest fixed point value (half away from zero).
+pub(crate) fn quantize<T: Sample, const M: usize>(
+    c: &[f64; M],
+    shift: u32,
+) -> Result<[T; M], &'static str> {
+    let scale = Float::powi(2f64, shift as _);
+    let mut q = [T::zero(); M];
+    for (q, c) in q.iter_mut().zip(c.iter()) {
+        *q = <T as NumCast>::from(Float::round(*c * scale)).ok_or("coefficient out of range")?;
+    }
+    Ok(q)
+}
+
+/// Convert fixed point coefficients with `shift` fractional bits to floating point.
+pub(crate) fn dequantize<T: Sample>(ba: &[T; 5], shift: u32) -> iir::Vec5<f64> {
+    let scale = Float::powi(2f64, -(shift as i32));
+    let mut c = [0.; 5];
+    for (c, ba) in c.iter_mut().zip(ba.iter()) {
+        *c = ba.to_f64().unwrap() * scale;
+    }
+    c
+}
+
+/// Cascade of `N` integer biquad IIR sections with common offset and limits.
+///
+/// See `iir::Cascade` for details. The sample type `T` and the coefficient format
+/// (`S` fractional bits) are the same as for `Biquad`.
+/// The outputs of the intermediate sections are not limited and saturate at the `T`
+/// range.
+#[derive(Copy, Clone, Debug, Miniconf)]
+pub struct Cascade<T, const S: u32, const N: usize> {
+    pub ba: [[T; 5]; N],
+    pub y_offset: T,
+    pub y_min: T,
+    pub y_max: T,
+}
+
+impl<T: Default + Copy, const S: u32, const N: usize> Default for Cascade<T, S, N> {
+    fn default() -> Self {
+        Self {
+            ba: [[T::default(); 5]; N],
+            y_offset: T::default(),
+            y_min: T::default(),
+            y_max: T::default(),
+        }
+    }
+}
+
+impl<T: Sample, const S: u32, const N: usize> Cascade<T, S, N> {
+    /// Coefficient fixed point format: `S` fractional bits, see `Biquad::SHIFT`.
+    pub const SHIFT: u32 = S;
+
+    /// Feed a new input value into the filter cascade, update the filter states, and
+    /// return the new output. Only the states `xy` are modified.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter states, one per section.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the outputs of all sections.
+    pub fn update(&self, xy: &mut [[T; 5]; N], x0: T, hold: bool) -> T {
+        let mut y0 = x0;
+        for (i, (ba, xy)) in self.ba.iter().zip(xy.iter_mut()).enumerate() {
+            // See `Biquad::update()`
+            xy.copy_within(0..4, 1);
+            xy[0] = y0;
+            y0 = if hold {
+                xy[3]
+            } else if i == N - 1 {
+                T::macc(self.y_offset, xy, ba, S)
+                    .max(self.y_min)
+                    .min(self.y_max)
+            } else {
+                T::macc(T::zero(), xy, ba, S)
+            };
+            xy[2] = y0;
+        }
         y0
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Coeff, Vec5};
+    use super::*;
+    use core::f64::consts::FRAC_1_SQRT_2;
 
     #[test]
     fn lowpass_gen() {
-        let ba = Vec5::lowpass(1e-5, 1. / 2f64.sqrt(), 2.);
-        println!("{:?}", ba);
+        let mut i = IIR::default();
+        i.set_lowpass(1e-5, Shape::Q(FRAC_1_SQRT_2), 2.).unwrap();
+        println!("{:?}", i.ba);
+        // Unity DC gain to within the quantization error
+        let k = i.ba[..3].iter().map(|b| *b as i64).sum::<i64>();
+        let a = (1i64 << IIR::SHIFT) - i.ba[3..].iter().map(|a| *a as i64).sum::<i64>();
+        assert!((k - 2 * a).abs() <= 3);
+    }
+
+    #[test]
+    fn quantize() {
+        let mut i = IIR::default();
+        i.set_ba(&[0.5, -1.0, 1.9999999995, -2.0, 1e-9]).unwrap();
+        assert_eq!(i.ba, [1 << 29, -1 << 30, i32::MAX, i32::MIN, 1]);
+        i.set_ba(&[-0.75 / (1 << 30) as f64, 0., 0., 0., 0.])
+            .unwrap();
+        assert_eq!(i.ba[0], -1);
+        assert!(i.set_ba(&[2., 0., 0., 0., 0.]).is_err());
+        assert!(i.set_ba(&[0., 0., 0., 0., f64::NAN]).is_err());
+        assert_eq!(i.ba[0], -1);
+    }
+
+    #[test]
+    fn validate() {
+        let mut i = IIR::default();
+        i.set_lowpass(1e-3, Shape::Q(FRAC_1_SQRT_2), 1.).unwrap();
+        assert_eq!(i.validate(), Ok(()));
+        for p in i.poles().iter() {
+            assert!(p.norm() < 1.);
+        }
+        let mut pi = iir::IIR::default();
+        pi.set_pi(1., 1e-3, f64::INFINITY).unwrap();
+        i.set_ba(&pi.ba).unwrap();
+        assert_eq!(i.validate(), Ok(()));
+        assert_eq!(i.poles()[0], Complex::new(1., 0.));
+        i.ba[3] += 1;
+        assert_eq!(i.validate(), Err(Invalid::Unstable));
+        i.ba = [1 << 30, 0, 0, 1 << 29, (1 << 29) + 1];
+        assert_eq!(i.validate(), Err(Invalid::Unstable));
+        i.ba[4] -= 1;
+        assert_eq!(i.validate(), Ok(()));
+        i.y_min = 1;
+        assert_eq!(i.validate(), Err(Invalid::Limits));
+    }
+
+    #[test]
+    fn bumpless() {
+        let mut i = IIR {
+            y_offset: 1000,
+            y_min: -1 << 20,
+            y_max: 1 << 20,
+            ..Default::default()
+        };
+        i.set_lowpass(1e-3, Shape::Q(FRAC_1_SQRT_2), 2.).unwrap();
+        let mut xy = i.state_for_output(12345).unwrap();
+        let x = xy[0];
+        for _ in 0..100 {
+            assert!((i.update(&mut xy, x, false) - 12345).abs() <= 1);
+        }
+        i.y_offset = 0;
+        let mut xy = i.state_for_input(-5000).unwrap();
+        assert_eq!(xy[2], -10000);
+        for _ in 0..100 {
+            assert!((i.update(&mut xy, -5000, false) + 10000).abs() <= 1);
+        }
+        assert!(i.state_for_output(1 << 21).is_err());
+
+        let mut pi = iir::IIR::default();
+        pi.set_pi(0.5, 1e-3, f64::INFINITY).unwrap();
+        i.set_ba(&pi.ba).unwrap();
+        assert!(i.state_for_input(0).is_err());
+        let mut xy = [0; 5];
+        for _ in 0..10 {
+            assert_eq!(i.track(&mut xy, 0, 3000), 3000);
+        }
+        assert_eq!(i.track(&mut xy, 0, i32::MAX), 1 << 20);
+        assert_eq!(i.track(&mut xy, 0, 3000), 3000);
+        assert_eq!(i.update(&mut xy, 0, false), 3000);
+
+        // Full range `i64`
+        let i = Biquad::<i64, 60> {
+            ba: [1 << 60, 0, 0, 0, 0],
+            y_offset: 0,
+            y_min: i64::MIN,
+            y_max: i64::MAX,
+        };
+        assert_eq!(i.state_for_input(i64::MAX).unwrap()[2], i64::MAX);
+        assert_eq!(i.state_for_input(i64::MIN).unwrap()[2], i64::MIN);
+    }
+
+    #[test]
+    fn block() {
+        let mut i = IIR {
+            y_min: -1 << 20,
+            y_max: 1 << 20,
+            ..Default::default()
+        };
+        i.set_lowpass(0.05, Shape::Q(2.), 1.).unwrap();
+        let x: Vec<i32> = (0..64).map(|n| ((n * 7) % 11) * 1000 - 5000).collect();
+        let mut xy0 = [0; 5];
+        let y0: Vec<i32> = x.iter().map(|x| i.update(&mut xy0, *x, false)).collect();
+
+        let mut xy = [0; 5];
+        let mut y = x.clone();
+        for y in y.chunks_mut(24) {
+            i.update_block(&mut xy, y, false);
+        }
+        assert_eq!(y, y0);
+        assert_eq!(xy, xy0);
+
+        let mut xy = [0; 5];
+        let mut y = vec![0; x.len()];
+        i.update_block_to(&mut xy, &x, &mut y, false);
+        assert_eq!(y, y0);
+
+        let mut xy = [[0; 5]; 2];
+        let mut y: Vec<i32> = x.iter().flat_map(|x| [*x, -*x]).collect();
+        i.update_interleaved(&mut xy, &mut y, false);
+        for (y, y0) in y.chunks_exact(2).zip(y0.iter()) {
+            assert_eq!(y[0], *y0);
+        }
+        assert_eq!(xy[0], xy0);
+
+        // Inverted limits are consistent between all paths
+        let i = IIR {
+            y_min: 10,
+            y_max: -10,
+            ..Default::default()
+        };
+        assert_eq!(i.update_clip(&mut [0; 5], 0, false), (-10, Some(Clip::Max)));
+        let mut y = [0; 2];
+        i.update_interleaved(&mut [[0; 5]; 2], &mut y, false);
+        assert_eq!(y, [-10; 2]);
+        assert_eq!(i.track(&mut [0; 5], 0, 0), -10);
+    }
+
+    #[test]
+    fn generic() {
+        // 16 bit samples, Q2.14 coefficients
+        let mut i = Biquad::<i16, 14> {
+            y_min: i16::MIN,
+            y_max: i16::MAX,
+            ..Default::default()
+        };
+        i.set_lowpass(0.01, Shape::Q(FRAC_1_SQRT_2), 1.).unwrap();
+        assert_eq!(i.validate(), Ok(()));
+        assert!(i.set_ba(&[2., 0., 0., 0., 0.]).is_err());
+        // Quantized DC gain
+        let ba = i.get_ba();
+        let k = (ba[0] + ba[1] + ba[2]) / (1. - ba[3] - ba[4]);
+        assert!((k - 1.).abs() < 0.05);
+        let mut xy = [0; 5];
+        let mut y = 0;
+        for _ in 0..1000 {
+            y = i.update(&mut xy, 10000, false);
+        }
+        // Rounding deadband
+        let d = 0.5 / (1. - ba[3] - ba[4]);
+        assert!((y as f64 - 10000. * k).abs() <= d);
+
+        // 64 bit samples, Q2.60 coefficients, very low corner frequency
+        let mut i = Biquad::<i64, 60> {
+            y_min: i64::MIN,
+            y_max: i64::MAX,
+            ..Default::default()
+        };
+        let mut f = iir::IIR::new(1., f64::NEG_INFINITY, f64::INFINITY);
+        f.set_lowpass(1e-6, Shape::Q(FRAC_1_SQRT_2), 1.).unwrap();
+        i.set_ba(&f.ba).unwrap();
+        // Reference with the quantized coefficients
+        f.ba = i.get_ba();
+        let (mut xy, mut xyf) = ([0; 5], [0.; 5]);
+        let x = 1i64 << 50;
+        for n in 1..=10000 {
+            let y = i.update(&mut xy, x, false);
+            let yf = f.update(&mut xyf, x as f64, false);
+            // Rounding errors accumulate in the (quasi) double integrator
+            assert!((y as f64 - yf).abs() <= (n * n) as f64);
+        }
+    }
+
+    #[test]
+    fn hold_clip() {
+        let i = IIR {
+            // Integrator
+            ba: [1 << 29, 0, 0, 1 << 30, 0],
+            y_min: -1000,
+            y_max: 1000,
+            ..Default::default()
+        };
+        let mut xy = [0; 5];
+        assert_eq!(i.update_clip(&mut xy, 500, false), (250, None));
+        assert_eq!(i.update_clip(&mut xy, 1000, false), (750, None));
+        assert_eq!(i.update_clip(&mut xy, 1000, false), (1000, Some(Clip::Max)));
+        // Frozen integrator
+        assert_eq!(i.update_clip(&mut xy, -100, true), (1000, None));
+        assert_eq!(i.update_clip(&mut xy, -100, true), (1000, None));
+        assert_eq!(i.update(&mut xy, -100, false), 950);
+        assert_eq!(
+            i.update_clip(&mut xy, -5000, false),
+            (-1000, Some(Clip::Min))
+        );
+    }
+
+    #[test]
+    fn convert() {
+        let mut f = iir::IIR::new(1., -0.5, f64::INFINITY);
+        f.set_lowpass(0.01, Shape::Q(FRAC_1_SQRT_2), 1.).unwrap();
+        f.y_offset = 0.25;
+        let (i, q) = IIR::from_float(&f, 1.).unwrap();
+        assert_eq!(i.y_offset, 1 << 29);
+        assert_eq!(i.y_min, -1 << 30);
+        assert_eq!(i.y_max, i32::MAX);
+        assert_eq!(i.ba, {
+            let mut j = IIR::default();
+            j.set_ba(&f.ba).unwrap();
+            j.ba
+        });
+        for d in q.poles.iter() {
+            assert!(*d > 0. && *d < 1e-7);
+        }
+        // Double zero at Nyquist: the quantized zeros split
+        for d in q.zeros.iter() {
+            assert!(*d < 1e-4);
+        }
+        assert!(q.dc_gain.abs() < 1e-6);
+        let g = i.to_float(1.);
+        assert_eq!(g.ba, i.get_ba());
+        assert_eq!((g.y_offset, g.y_min), (0.25, -0.5));
+
+        // Unchanged units
+        f.y_offset = -1234.;
+        f.y_min = -1e5;
+        f.y_max = 1e5;
+        let i = IIR::try_from(f).unwrap();
+        assert_eq!((i.y_offset, i.y_min, i.y_max), (-1234, -100_000, 100_000));
+        let g = iir::IIR::from(i);
+        assert_eq!((g.y_offset, g.y_min, g.y_max), (-1234., -1e5, 1e5));
+        f.y_offset = 1e10;
+        assert!(IIR::try_from(f).is_err());
+
+        // Coarse quantization
+        f.y_offset = 0.;
+        let (_, q) = Biquad::<i16, 14>::from_float(&f, 1.).unwrap();
+        assert!(q.poles[0] > 1e-5);
+        assert!(q.dc_gain.abs() > 1e-2);
+    }
+
+    #[test]
+    fn cascade() {
+        let mut c = Cascade::<i32, 30, 2>::default();
+        c.set("y_min", b"-1000000").unwrap();
+        c.set("y_max", b"1000000").unwrap();
+        c.y_offset = 1234;
+        let mut i = [IIR::default(); 2];
+        i[0].set_lowpass(0.01, Shape::Q(0.7), 1.).unwrap();
+        let mut pi = iir::IIR::default();
+        pi.set_pi(1.5, 1e-3, 100.).unwrap();
+        i[1].set_ba(&pi.ba).unwrap();
+        i[0].y_min = i32::MIN;
+        i[0].y_max = i32::MAX;
+        i[1].y_min = c.y_min;
+        i[1].y_max = c.y_max;
+        i[1].y_offset = c.y_offset;
+        c.ba = [i[0].ba, i[1].ba];
+        let mut xy = [Vec5::default(); 2];
+        let mut xyi = [Vec5::default(); 2];
+        for n in 0..1000 {
+            let x = ((n * 7) % 11) * 1000 - 2000;
+            let y = c.update(&mut xy, x, false);
+            let yi = i
+                .iter()
+                .zip(xyi.iter_mut())
+                .fold(x, |x, (i, xy)| i.update(xy, x, false));
+            assert_eq!(y, yi);
+        }
+        assert_eq!(xy, xyi);
+
+        // 16 bit samples, Q2.14 coefficients
+        let mut c = Cascade::<i16, 14, 2> {
+            y_min: i16::MIN,
+            y_max: i16::MAX,
+            ..Default::default()
+        };
+        let mut i = [Biquad::<i16, 14>::default(); 2];
+        i[0].set_lowpass(0.05, Shape::Q(0.7), 1.).unwrap();
+        i[1].set_highpass(0.01, Shape::Q(0.7), 1.).unwrap();
+        for i in i.iter_mut() {
+            i.y_min = i16::MIN;
+            i.y_max = i16::MAX;
+        }
+        c.ba = [i[0].ba, i[1].ba];
+        let (mut xy, mut xyi) = ([[0; 5]; 2], [[0; 5]; 2]);
+        for n in 0..1000 {
+            let x = ((n * 7) % 11) * 1000 - 5000;
+            let y = c.update(&mut xy, x, false);
+            let yi = i[0].update(&mut xyi[0], x, false);
+            assert_eq!(y, i[1].update(&mut xyi[1], yi, false));
+        }
+    }
+
+    #[test]
+    fn full_band() {
+        let mut i = IIR::default();
+        i.set_lowpass(0.45, Shape::Q(FRAC_1_SQRT_2), 1.).unwrap();
+        let mut f = iir::IIR::<f64>::default();
+        f.set_lowpass(0.45, Shape::Q(FRAC_1_SQRT_2), 1.).unwrap();
+        for (i, f) in i.ba.iter().zip(f.ba.iter()) {
+            assert!(
+                (*i as f64 / (1 << IIR::SHIFT) as f64 - f).abs() <= 0.5 / (1 << IIR::SHIFT) as f64
+            );
+        }
+        assert!(i.set_highshelf(0.3, Shape::Slope(1.), 100.).is_err());
+        assert!(i.set_notch(0.7, Shape::Q(1.), 1.).is_err());
     }
 }
@@ -0,0 +1,182 @@
+use super::{iir::StateN, IIR};
+use crate::tools::macc_i64;
+use miniconf::Miniconf;
+
+/// Integer direct form I IIR filter of order `N`.
+///
+/// See `iir::IIRN` for the structure and `iir::StateN` for the state layout.
+/// The coefficients have `S` fractional bits. The feed-back coefficients of
+/// higher order filters with low corner frequencies approach the binomial coefficients
+/// (e.g. `a1 = 3` for `N = 3`) and may need a smaller `S` than `IIR` (Q2.30).
+/// All `2N + 1` products are accumulated at full precision and rounded once
+/// (like `macc_i32()`).
+///
+/// # Miniconf
+///
+/// `{"b0": b0, "b": [b1, .., bN], "a": [a1, .., aN], "y_offset": y_offset, "y_min": y_min, "y_max": y_max}`
+#[derive(Copy, Clone, Debug, Miniconf)]
+pub struct IIRN<const N: usize, const S: u32 = 30> {
+    pub b0: i32,
+    pub b: [i32; N],
+    pub a: [i32; N],
+    pub y_offset: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+}
+
+impl<const N: usize, const S: u32> Default for IIRN<N, S> {
+    fn default() -> Self {
+        Self {
+            b0: 0,
+            b: [0; N],
+            a: [0; N],
+            y_offset: 0,
+            y_min: 0,
+            y_max: 0,
+        }
+    }
+}
+
+impl<const N: usize, const S: u32> IIRN<N, S> {
+    /// Coefficient fixed point format: `S` fractional bits.
+    pub const SHIFT: u32 = S;
+
+    /// Feed a new input value into the filter, update the filter state, and
+    /// return the new output. Only the state `xy` is modified.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the output, see `iir::IIR::update()`.
+    pub fn update(&self, xy: &mut StateN<i32, N>, x0: i32, hold: bool) -> i32 {
+        debug_assert!(N > 0);
+        let shift = S;
+        let y0 = if hold {
+            xy[1][0]
+        } else {
+            // See `macc_i32()`, rounding bias, half up
+            let y0 = ((self.y_offset as i64) << shift) + (1 << (shift - 1));
+            let y = macc_i64(y0, &[x0], &[self.b0], &xy[0], &self.b);
+            let y = macc_i64(y, &xy[1], &self.a, &[], &[]);
+            (y >> shift).max(i32::MIN as _).min(i32::MAX as _) as _
+        };
+        let y0 = y0.max(self.y_min).min(self.y_max);
+        xy[0].copy_within(0..N - 1, 1);
+        xy[0][0] = x0;
+        xy[1].copy_within(0..N - 1, 1);
+        xy[1][0] = y0;
+        y0
+    }
+}
+
+impl From<IIR> for IIRN<2, 30> {
+    fn from(iir: IIR) -> Self {
+        let ba = iir.ba;
+        Self {
+            b0: ba[0],
+            b: [ba[1], ba[2]],
+            a: [ba[3], ba[4]],
+            y_offset: iir.y_offset,
+            y_min: iir.y_min,
+            y_max: iir.y_max,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iir::{self, FrequencyResponse, Shape};
+    use crate::Complex;
+    use core::f64::consts::PI;
+
+    #[test]
+    fn biquad() {
+        let mut i = IIR {
+            y_offset: 1234,
+            y_min: -30000,
+            y_max: 40000,
+            ..Default::default()
+        };
+        i.set_lowpass(0.01, Shape::Q(3.), 2.).unwrap();
+        let n = IIRN::from(i);
+        let (mut xy, mut xyn) = ([0; 5], [[0; 2]; 2]);
+        for k in 0..2000 {
+            let x = ((k * 7) % 11) * 3000 - 15000;
+            let hold = k % 17 == 0;
+            assert_eq!(i.update(&mut xy, x, hold), n.update(&mut xyn, x, hold));
+        }
+    }
+
+    #[test]
+    fn rails() {
+        // Large coefficients and full scale inputs overflow the `i64` accumulator
+        let i = IIR {
+            ba: [i32::MIN, i32::MAX, 0, i32::MIN, 0],
+            y_offset: -1234,
+            y_min: i32::MIN,
+            y_max: i32::MAX,
+        };
+        let n = IIRN::<1> {
+            b0: i32::MIN,
+            b: [i32::MAX],
+            a: [i32::MIN],
+            y_offset: -1234,
+            y_min: i32::MIN,
+            y_max: i32::MAX,
+        };
+        let (mut xy, mut xyn) = ([0; 5], [[0; 1]; 2]);
+        for k in 0..200 {
+            let x = match k % 5 {
+                0 | 1 => i32::MIN,
+                2 => i32::MAX,
+                3 => 0,
+                _ => (k * 12345) << 16,
+            };
+            assert_eq!(i.update(&mut xy, x, false), n.update(&mut xyn, x, false));
+        }
+    }
+
+    #[test]
+    fn third_order() {
+        // First order lowpass times biquad lowpass
+        let mut f = iir::IIR::<f64>::default();
+        f.set_lowpass(0.02, Shape::Q(1.), 1.).unwrap();
+        let (p, ba) = (0.9, f.ba);
+        // a1 is close to 3: Q4.28
+        let scale = (1u64 << IIRN::<3, 28>::SHIFT) as f64;
+        let q = |c: f64| (c * scale).round() as i32;
+        let n = IIRN::<3, 28> {
+            b0: q((1. - p) * ba[0]),
+            b: [q((1. - p) * ba[1]), q((1. - p) * ba[2]), 0],
+            a: [q(p + ba[3]), q(ba[4] - p * ba[3]), q(-p * ba[4])],
+            y_offset: 0,
+            y_min: i32::MIN,
+            y_max: i32::MAX,
+        };
+        let nf = iir::IIRN::<f64, 3> {
+            b0: n.b0 as f64 / scale,
+            b: [n.b[0] as f64 / scale, n.b[1] as f64 / scale, 0.],
+            a: [
+                n.a[0] as f64 / scale,
+                n.a[1] as f64 / scale,
+                n.a[2] as f64 / scale,
+            ],
+            ..iir::IIRN::new(0., f64::NEG_INFINITY, f64::INFINITY)
+        };
+        let h = nf.response(0.01);
+        let z = Complex::from_polar(1., -2. * PI * 0.01);
+        let h0 = f.response(0.01) * (1. - p) / (1. - z * p);
+        assert!((h - h0).norm() < 1e-5);
+        // Rounding errors are amplified by the DC gain of the recursion
+        let e = 1. / (1. - nf.a.iter().sum::<f64>());
+        let (mut xy, mut xyf) = ([[0; 3]; 2], [[0.; 3]; 2]);
+        for k in 0..1000 {
+            let x = if k < 500 { 1 << 20 } else { -(1 << 18) };
+            let y = n.update(&mut xy, x, false);
+            let yf = nf.update(&mut xyf, x as f64, false);
+            assert!((y as f64 - yf).abs() < e);
+        }
+        assert!(((n.update(&mut xy, -(1 << 18), false) + (1 << 18)) as f64).abs() < e);
+    }
+}
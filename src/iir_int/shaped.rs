@@ -0,0 +1,184 @@
+use super::IIR;
+use crate::tools::macc_i64;
+use miniconf::Miniconf;
+
+/// State vector of `ShapedIIR`: `[x0, x1, y0, y1, y2, e0, e1]`.
+///
+/// The first five elements are the same as for `IIR` (see `Vec5`).
+/// `e0` and `e1` are the two most recent output quantization errors (residues).
+pub type Vec7 = [i32; 7];
+
+/// Integer biquad IIR with error feedback
+///
+/// Rounding the accumulator to the output leaves a quantization error (residue).
+/// `IIR` discards it. For filters with large gain in the recursion (lowpasses with
+/// corner frequencies far below the sample rate) the error is amplified by up to
+/// `1/(1 - a1 - a2)` and leads to large DC errors (deadband) and limit cycles.
+///
+/// `ShapedIIR` keeps the last two residues in the state and feeds them back into
+/// the accumulator with the integer weights `ef`. This shapes the quantization noise
+/// spectrum by `1 - ef[0]*z^-1 - ef[1]*z^-2`:
+/// * `ShapedIIR::EF1` (`[1, 0]`): first order, one zero at DC.
+///   Removes the rounding bias.
+/// * `ShapedIIR::EF2` (`[2, -1]`): second order, double zero at DC.
+///   Matches the double pole close to DC of a low corner frequency lowpass.
+///
+/// With `ef = [0, 0]` the filter is equivalent to `IIR`.
+///
+/// # Miniconf
+///
+/// `{"iir": {"y_offset": y_offset, "y_min": y_min, "y_max": y_max, "ba": [b0, b1, b2, a1, a2]}, "ef": [ef1, ef2]}`
+///
+/// See `IIR` for the filter items.
+#[derive(Copy, Clone, Debug, Default, Miniconf)]
+pub struct ShapedIIR {
+    pub iir: IIR,
+    pub ef: [i32; 2],
+}
+
+impl ShapedIIR {
+    /// First order error feedback
+    pub const EF1: [i32; 2] = [1, 0];
+    /// Second order error feedback
+    pub const EF2: [i32; 2] = [2, -1];
+
+    pub fn new(iir: IIR, ef: [i32; 2]) -> Self {
+        Self { iir, ef }
+    }
+
+    /// Feed a new input value into the filter, update the filter state, and
+    /// return the new output. Only the state `xy` is modified.
+    ///
+    /// When the output is limited or held, the residue is discarded.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state.
+    /// * `x0` - New input.
+    /// * `hold` - Hold the output, see `iir::IIR::update()`.
+    pub fn update(&self, xy: &mut Vec7, x0: i32, hold: bool) -> i32 {
+        let shift = IIR::SHIFT;
+        // See `IIR::update()`
+        xy.copy_within(0..4, 1);
+        xy[0] = x0;
+        let (y0, e0) = if hold {
+            (xy[3], 0)
+        } else {
+            // Rounding bias, half up
+            let y0 = ((self.iir.y_offset as i64) << shift) + (1 << (shift - 1));
+            let y = macc_i64(y0, &xy[..5], &self.iir.ba, &xy[5..], &self.ef);
+            let y0 = y >> shift;
+            // Residue without the rounding bias
+            let e0 = (y & ((1 << shift) - 1)) - (1 << (shift - 1));
+            let y = y0.max(self.iir.y_min as i64).min(self.iir.y_max as i64);
+            if y != y0 {
+                (y as _, 0)
+            } else {
+                (y0 as _, e0 as _)
+            }
+        };
+        xy[2] = y0;
+        xy[6] = xy[5];
+        xy[5] = e0;
+        y0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iir::{self, Shape};
+    use core::f64::consts::FRAC_1_SQRT_2;
+
+    fn lowpass(f: f64, q: f64) -> (IIR, iir::IIR<f64>) {
+        let mut i = IIR {
+            y_min: i32::MIN,
+            y_max: i32::MAX,
+            ..Default::default()
+        };
+        i.set_lowpass(f, Shape::Q(q), 1.).unwrap();
+        // Reference with the quantized coefficients
+        let f = iir::IIR {
+            ba: i.get_ba(),
+            y_offset: 0.,
+            y_min: f64::NEG_INFINITY,
+            y_max: f64::INFINITY,
+        };
+        (i, f)
+    }
+
+    #[test]
+    fn equivalent() {
+        let (mut i, _) = lowpass(0.01, 2.);
+        i.y_offset = 100;
+        i.y_min = -20000;
+        i.y_max = 20000;
+        let s = ShapedIIR::new(i, [0, 0]);
+        let (mut xy, mut xyi) = ([0; 7], [0; 5]);
+        for n in 0..1000 {
+            let x = ((n * 7) % 11) * 3000 - 15000;
+            let hold = n % 13 == 0;
+            assert_eq!(s.update(&mut xy, x, hold), i.update(&mut xyi, x, hold));
+        }
+    }
+
+    #[test]
+    fn deadband() {
+        let (i, f) = lowpass(1e-4, FRAC_1_SQRT_2);
+        let x = 12345;
+        for ef in [[0, 0], ShapedIIR::EF1, ShapedIIR::EF2].iter() {
+            let s = ShapedIIR::new(i, *ef);
+            let (mut xy, mut xyf) = ([0; 7], [0.; 5]);
+            let mut emax = 0f64;
+            let mut y = 0;
+            // Step up and back down
+            for n in 0..400_000 {
+                let x = if n < 200_000 { x } else { 0 };
+                y = s.update(&mut xy, x, false);
+                let yf = f.update(&mut xyf, x as f64, false);
+                emax = emax.max((y as f64 - yf).abs());
+                if n == 199_999 {
+                    if *ef == [0, 0] {
+                        // Entirely within the deadband: `b*x` rounds to zero
+                        assert_eq!(y, 0);
+                    } else {
+                        assert_eq!(y, x);
+                    }
+                }
+            }
+            assert_eq!(y, 0);
+            match *ef {
+                // No DC error but the transient error is large
+                ShapedIIR::EF1 => assert!(emax < 500.),
+                // Tracks the reference closely
+                ShapedIIR::EF2 => assert!(emax < 1.5),
+                _ => assert!(emax > x as f64),
+            }
+        }
+    }
+
+    #[test]
+    fn limit_cycle() {
+        let (i, _) = lowpass(0.005, 5.);
+        for ef in [[0, 0], ShapedIIR::EF2].iter() {
+            let s = ShapedIIR::new(i, *ef);
+            let mut xy = [0; 7];
+            for _ in 0..1000 {
+                s.update(&mut xy, 100_000, false);
+            }
+            // Free decay
+            let mut ymax = 0;
+            for n in 0..100_000 {
+                let y = s.update(&mut xy, 0, false);
+                if n > 90_000 {
+                    ymax = ymax.max(y.abs());
+                }
+            }
+            if *ef == [0, 0] {
+                // Limit cycle
+                assert!(ymax > 100);
+            } else {
+                assert!(ymax <= 1);
+            }
+        }
+    }
+}
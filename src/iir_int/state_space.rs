@@ -0,0 +1,135 @@
+use super::{iir, quantize};
+use crate::tools::macc_i64;
+use miniconf::Miniconf;
+
+/// Integer discrete time state-space system.
+///
+/// See `iir::StateSpace` for the structure and the matrix layout.
+/// The matrix elements have `S` fractional bits. Each output and each new
+/// state is accumulated at full precision, rounded once (like `macc_i32()`), and
+/// saturated to the `i32` range.
+///
+/// # Miniconf
+///
+/// `{"a": [[a00, ..], ..], "b": [[b00, ..], ..], "c": [[c00, ..], ..], "d": [[d00, ..], ..]}`
+#[derive(Copy, Clone, Debug, Miniconf)]
+pub struct StateSpace<const N: usize, const I: usize, const O: usize, const S: u32 = 30> {
+    pub a: [[i32; N]; N],
+    pub b: [[i32; I]; N],
+    pub c: [[i32; N]; O],
+    pub d: [[i32; I]; O],
+}
+
+impl<const N: usize, const I: usize, const O: usize, const S: u32> Default
+    for StateSpace<N, I, O, S>
+{
+    fn default() -> Self {
+        Self {
+            a: [[0; N]; N],
+            b: [[0; I]; N],
+            c: [[0; N]; O],
+            d: [[0; I]; O],
+        }
+    }
+}
+
+/// Multiply-accumulate state and input with rounding and saturation.
+fn macc_sat(x: &[i32], a: &[i32], u: &[i32], b: &[i32], shift: u32) -> i32 {
+    // Rounding bias, half up
+    let y = macc_i64(1 << (shift - 1), x, a, u, b);
+    (y >> shift).max(i32::MIN as _).min(i32::MAX as _) as _
+}
+
+impl<const N: usize, const I: usize, const O: usize, const S: u32> StateSpace<N, I, O, S> {
+    /// Matrix element fixed point format: `S` fractional bits.
+    pub const SHIFT: u32 = S;
+
+    /// Quantize a floating point state-space system.
+    ///
+    /// The elements are rounded to the nearest fixed point value, see `Biquad::set_ba()`.
+    ///
+    /// # Arguments
+    /// * `ss` - Floating point system.
+    pub fn from_float(ss: &iir::StateSpace<f64, N, I, O>) -> Result<Self, &'static str> {
+        let mut s = Self::default();
+        for (s, m) in s.a.iter_mut().zip(ss.a.iter()) {
+            *s = quantize(m, S)?;
+        }
+        for (s, m) in s.b.iter_mut().zip(ss.b.iter()) {
+            *s = quantize(m, S)?;
+        }
+        for (s, m) in s.c.iter_mut().zip(ss.c.iter()) {
+            *s = quantize(m, S)?;
+        }
+        for (s, m) in s.d.iter_mut().zip(ss.d.iter()) {
+            *s = quantize(m, S)?;
+        }
+        Ok(s)
+    }
+
+    /// Feed new inputs into the system, update the state, and
+    /// return the new outputs. Only the state `x` is modified.
+    ///
+    /// # Arguments
+    /// * `x` - Current state.
+    /// * `u` - New inputs.
+    pub fn update(&self, x: &mut [i32; N], u: &[i32; I]) -> [i32; O] {
+        let mut y = [0; O];
+        for (y, (c, d)) in y.iter_mut().zip(self.c.iter().zip(self.d.iter())) {
+            *y = macc_sat(x, c, u, d, S);
+        }
+        let mut x1 = [0; N];
+        for (x1, (a, b)) in x1.iter_mut().zip(self.a.iter().zip(self.b.iter())) {
+            *x1 = macc_sat(x, a, u, b, S);
+        }
+        *x = x1;
+        y
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iir::Shape;
+
+    #[test]
+    fn biquad() {
+        let mut i = iir::IIR::new(1., f64::NEG_INFINITY, f64::INFINITY);
+        i.set_lowpass(0.05, Shape::Q(1.), 1.).unwrap();
+        let f = iir::StateSpace::from(i);
+        let s = StateSpace::<2, 1, 1>::from_float(&f).unwrap();
+        let (mut x, mut xf) = ([0; 2], [0.; 2]);
+        for k in 0..1000 {
+            let u = if k < 500 { 1 << 20 } else { -(1 << 24) };
+            let y = s.update(&mut x, &[u])[0];
+            let yf = f.update(&mut xf, &[u as f64])[0];
+            assert!((y as f64 - yf).abs() < 20.);
+        }
+        // Q2.30 range
+        assert!(StateSpace::<2, 1, 1>::from_float(&iir::StateSpace {
+            b: [[4.], [0.]],
+            ..f
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn saturate() {
+        let s = StateSpace::<1, 1, 1> {
+            a: [[1 << 30]],
+            b: [[1 << 30]],
+            c: [[1 << 30]],
+            d: [[1 << 29]],
+        };
+        let mut x = [0];
+        let mut y = 0;
+        for _ in 0..1000 {
+            let y1 = s.update(&mut x, &[1 << 28])[0];
+            assert!(y1 >= y);
+            y = y1;
+        }
+        assert_eq!(x, [i32::MAX]);
+        assert_eq!(y, i32::MAX);
+        assert_eq!(s.update(&mut x, &[i32::MIN])[0], i32::MAX - (1 << 30));
+    }
+}
@@ -12,16 +12,26 @@ mod cossin;
 pub use cossin::*;
 pub mod iir;
 pub mod iir_int;
+mod lock;
+pub use lock::*;
 mod lockin;
 pub use lockin::*;
 mod lowpass;
 pub use lowpass::*;
 mod pll;
 pub use pll::*;
+mod pll3;
+pub use pll3::*;
+mod pll64;
+pub use pll64::*;
 mod rpll;
 pub use rpll::*;
+mod slew;
+pub use slew::*;
 mod unwrap;
 pub use unwrap::*;
+mod unwrapping_pll;
+pub use unwrapping_pll::*;
 
 #[cfg(test)]
 pub mod testing;
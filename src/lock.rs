@@ -0,0 +1,373 @@
+use super::{PLL, RPLL};
+use miniconf::Miniconf;
+use serde::{Deserialize, Serialize};
+
+/// Phase and frequency observables of a PLL for lock detection.
+pub trait Lock {
+    /// Phase error of the current phase estimate with respect to the input of
+    /// the last `update()` (wrapping, full turn is `1 << 32`).
+    fn phase_error(&self, input: i32) -> i32;
+
+    /// Current frequency estimate (wrapping).
+    fn frequency_estimate(&self) -> i32;
+}
+
+impl Lock for PLL {
+    fn phase_error(&self, input: i32) -> i32 {
+        input.wrapping_sub(self.phase())
+    }
+
+    fn frequency_estimate(&self) -> i32 {
+        self.frequency()
+    }
+}
+
+impl Lock for RPLL {
+    /// The input is the timestamp of the last `update()`.
+    fn phase_error(&self, input: i32) -> i32 {
+        // See `RPLL::update()`: reference phase "now"
+        let dt = (input.wrapping_neg() & ((1 << self.dt2) - 1)) as u32;
+        let y_ref = (self.frequency() >> self.dt2).wrapping_mul(dt) as i32;
+        y_ref.wrapping_sub(self.phase())
+    }
+
+    fn frequency_estimate(&self) -> i32 {
+        self.frequency() as i32
+    }
+}
+
+/// Lock detector configuration
+///
+/// # Miniconf
+///
+/// `{"shift": shift, "phase_lock": pl, "phase_unlock": pu, "frequency_lock": fl, "frequency_unlock": fu, "holdover": n}`
+///
+/// * `shift` is the log2 time constant (in updates) of the error filters, `1..=31`.
+///   Out of range values are clamped.
+/// * `pl` and `pu` are the thresholds for the filtered phase error magnitude
+///   (full turn is `1 << 32`) to declare lock and loss of lock.
+/// * `fl` and `fu` are the thresholds for the filtered frequency error variance
+///   (mean square of the frequency estimate changes) to declare lock and loss of lock.
+/// * `n` is the holdover timeout: the number of consecutive missing inputs after which
+///   lock is lost. While the PLL free-runs on missing inputs the lock state is held
+///   until the timeout. Zero for no timeout. For `RPLL` the inputs are the timestamps
+///   and are missing in all updates between two timestamps. The timeout must then be
+///   larger than the reference period in updates (including jitter).
+///
+/// Lock is declared once both filtered errors are below their lock threshold and lost
+/// once either exceeds its unlock threshold. The unlock thresholds should be larger than
+/// the lock thresholds (hysteresis).
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize, Miniconf)]
+pub struct LockConfig {
+    pub shift: u32,
+    pub phase_lock: u32,
+    pub phase_unlock: u32,
+    pub frequency_lock: u64,
+    pub frequency_unlock: u64,
+    pub holdover: u32,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            shift: 8,
+            phase_lock: 1 << 20,
+            phase_unlock: 1 << 24,
+            frequency_lock: 1 << 24,
+            frequency_unlock: 1 << 32,
+            holdover: 0,
+        }
+    }
+}
+
+/// Lock state of a `LockDetector`
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LockState {
+    /// Not locked
+    Unlocked,
+    /// Locked and the last input was present
+    Locked,
+    /// Locked and free-running on missing inputs, before the holdover timeout
+    Holdover,
+}
+
+/// PLL lock detector
+///
+/// Tracks the filtered phase error magnitude and the filtered frequency error variance
+/// of a `PLL` or `RPLL` (see `Lock`), the lock state with hysteresis (see `LockConfig`),
+/// the number of updates since lock was acquired, and the number of cycle slips.
+///
+/// A cycle slip is counted whenever the phase error wraps (crosses a half turn)
+/// between two updates. This includes slips during lock acquisition.
+///
+/// The filtered errors start at full scale and the detector starts unlocked.
+/// They are reset to full scale on holdover timeout.
+#[derive(Copy, Clone, Debug)]
+pub struct LockDetector {
+    pub config: LockConfig,
+    // filtered phase error magnitude
+    phase: i64,
+    // filtered frequency error variance
+    frequency: i64,
+    // last phase error
+    e: i32,
+    // last frequency estimate
+    f: i32,
+    // updates since lock
+    locked: Option<u32>,
+    slips: u32,
+    // consecutive missing inputs
+    missing: u32,
+}
+
+impl LockDetector {
+    pub fn new(config: LockConfig) -> Self {
+        Self {
+            config,
+            phase: 1 << 31,
+            frequency: 1 << 62,
+            e: 0,
+            f: 0,
+            locked: None,
+            slips: 0,
+            missing: 0,
+        }
+    }
+
+    /// Reset the detector to its initial (unlocked) state.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// Update the detector after a PLL update.
+    ///
+    /// Args:
+    /// * `pll`: The PLL after its `update()`.
+    /// * `input`: The input passed to that `update()`. `None` if it was missing.
+    ///   For missing inputs only the frequency error is updated and the holdover
+    ///   timeout is checked.
+    ///
+    /// Returns:
+    /// `true` if locked (including holdover), see `state()`.
+    pub fn update<P: Lock>(&mut self, pll: &P, input: Option<i32>) -> bool {
+        let shift = self.config.shift.clamp(1, 31);
+        let f = pll.frequency_estimate();
+        let df = f.wrapping_sub(self.f) as i64;
+        self.f = f;
+        self.frequency += (df * df - self.frequency) >> shift;
+        if let Some(x) = input {
+            let e = pll.phase_error(x);
+            // Opposite signs and more than a half turn apart
+            if (e ^ self.e) < 0 && (e as i64 - self.e as i64).abs() > 1 << 31 {
+                self.slips = self.slips.wrapping_add(1);
+            }
+            self.e = e;
+            self.phase += ((e as i64).abs() - self.phase) >> shift;
+            self.missing = 0;
+        } else {
+            self.missing = self.missing.saturating_add(1);
+        }
+        let c = &self.config;
+        if c.holdover != 0 && self.missing >= c.holdover {
+            // Holdover timeout: require reacquisition
+            self.phase = 1 << 31;
+            self.frequency = 1 << 62;
+            self.e = 0;
+            self.locked = None;
+            return false;
+        }
+        self.locked = match self.locked {
+            None if self.phase <= c.phase_lock as i64
+                && self.frequency as u64 <= c.frequency_lock =>
+            {
+                Some(0)
+            }
+            Some(_)
+                if self.phase > c.phase_unlock as i64
+                    || self.frequency as u64 > c.frequency_unlock =>
+            {
+                None
+            }
+            Some(n) => Some(n.saturating_add(1)),
+            None => None,
+        };
+        self.is_locked()
+    }
+
+    /// Whether the PLL is locked.
+    pub fn is_locked(&self) -> bool {
+        self.locked.is_some()
+    }
+
+    /// Number of updates since lock was acquired, `None` if not locked.
+    pub fn time_locked(&self) -> Option<u32> {
+        self.locked
+    }
+
+    /// Number of consecutive missing inputs if in holdover (locked and inputs missing).
+    ///
+    /// For `RPLL` this includes the updates between two timestamps, see `LockConfig`.
+    pub fn holdover(&self) -> Option<u32> {
+        self.locked.and(Some(self.missing)).filter(|&n| n > 0)
+    }
+
+    /// Current lock state.
+    pub fn state(&self) -> LockState {
+        match (self.locked, self.missing) {
+            (None, _) => LockState::Unlocked,
+            (Some(_), 0) => LockState::Locked,
+            (Some(_), _) => LockState::Holdover,
+        }
+    }
+
+    /// Number of cycle slips (wrapping).
+    pub fn slips(&self) -> u32 {
+        self.slips
+    }
+
+    /// Filtered phase error magnitude.
+    pub fn phase_error(&self) -> i64 {
+        self.phase
+    }
+
+    /// Filtered frequency error variance.
+    pub fn frequency_variance(&self) -> i64 {
+        self.frequency
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pll() {
+        let mut p = PLL::default();
+        let mut d = LockDetector::new(LockConfig::default());
+        let mut f0 = 0x1234_5678_i32;
+        let mut x = 0i32;
+        let mut lock = None;
+        let mut slips = 0;
+        for i in 0..1 << 16 {
+            if i == 1 << 15 {
+                // Frequency jump
+                f0 = -0x2345_6789;
+            }
+            x = x.wrapping_add(f0);
+            p.update(Some(x), 10, 9);
+            let locked = d.update(&p, Some(x));
+            if i < 1 << 15 {
+                if locked && lock.is_none() {
+                    lock = Some(i);
+                    slips = d.slips();
+                }
+                if let Some(l) = lock {
+                    assert!(locked);
+                    assert_eq!(d.time_locked(), Some(i - l));
+                    assert_eq!(d.slips(), slips);
+                }
+            } else if i == (1 << 15) + 10 {
+                assert!(!locked);
+            }
+        }
+        assert!(lock.unwrap() < 1 << 14);
+        // Relocked after slipping cycles
+        assert!(d.is_locked());
+        assert!(d.slips() > slips);
+    }
+
+    #[test]
+    fn shift() {
+        // Out of range filter time constants are clamped
+        for shift in [0, 32, u32::MAX].iter() {
+            let mut d = LockDetector::new(LockConfig {
+                shift: *shift,
+                ..LockConfig::default()
+            });
+            let p = PLL::default();
+            d.update(&p, Some(0));
+            d.update(&p, None);
+        }
+    }
+
+    #[test]
+    fn holdover() {
+        let f0 = 0x1234_5678_i32;
+        let mut p = PLL::new(0, f0);
+        let mut d = LockDetector::new(LockConfig {
+            holdover: 100,
+            ..LockConfig::default()
+        });
+        let mut x = 0i32;
+        let mut run = |p: &mut PLL, d: &mut LockDetector, n, valid| {
+            for _ in 0..n {
+                x = x.wrapping_add(f0);
+                let xi = if valid { Some(x) } else { None };
+                p.update(xi, 10, 9);
+                d.update(p, xi);
+                // Free running on the frequency estimate
+                assert!(x.wrapping_sub(p.phase()).abs() < 1 << 16);
+            }
+        };
+        assert_eq!(d.state(), LockState::Unlocked);
+        run(&mut p, &mut d, 1 << 13, true);
+        assert!(d.is_locked());
+        assert_eq!(d.holdover(), None);
+        assert_eq!(d.state(), LockState::Locked);
+        run(&mut p, &mut d, 99, false);
+        assert!(d.is_locked());
+        assert_eq!(d.holdover(), Some(99));
+        assert_eq!(d.state(), LockState::Holdover);
+        run(&mut p, &mut d, 1, true);
+        assert!(d.is_locked());
+        assert_eq!(d.holdover(), None);
+        assert_eq!(d.state(), LockState::Locked);
+        run(&mut p, &mut d, 100, false);
+        assert!(!d.is_locked());
+        assert_eq!(d.holdover(), None);
+        assert_eq!(d.state(), LockState::Unlocked);
+        // Reacquisition
+        run(&mut p, &mut d, 10, true);
+        assert!(!d.is_locked());
+        run(&mut p, &mut d, 1 << 13, true);
+        assert!(d.is_locked());
+        d.reset();
+        assert!(!d.is_locked());
+        assert_eq!(d.config.holdover, 100);
+    }
+
+    #[test]
+    fn rpll() {
+        let dt2 = 8;
+        let mut p = RPLL::new(dt2);
+        let period = 333;
+        let mut d = LockDetector::new(LockConfig {
+            shift: 6,
+            // Longer than the reference period in updates
+            holdover: 2,
+            ..LockConfig::default()
+        });
+        let mut next = 111i32;
+        let mut time = 0i32;
+        let mut slips = None;
+        for i in 0..1 << 14 {
+            let timestamp = if time.wrapping_sub(next) >= 0 {
+                let t = next;
+                next = next.wrapping_add(period);
+                Some(t)
+            } else {
+                None
+            };
+            p.update(timestamp, 9, 8);
+            let locked = d.update(&p, timestamp);
+            if i > 1 << 13 {
+                assert!(locked);
+                // No slips after acquisition
+                assert_eq!(*slips.get_or_insert(d.slips()), d.slips());
+            } else if i < 10 {
+                assert!(!locked);
+            }
+            time = time.wrapping_add(1 << dt2);
+        }
+    }
+}
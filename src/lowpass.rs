@@ -34,7 +34,7 @@ impl<const N: usize> Lowpass<N> {
             *y += dy;
             x = *y - (dy >> 1);
         }
-        x.saturating_add((N as i32) << (k - 1).max(0))
+        x.saturating_add((N as i32) << (k - 1))
     }
 
     /// Return the current filter output
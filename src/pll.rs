@@ -1,5 +1,70 @@
 use serde::{Deserialize, Serialize};
 
+// PLL update and accessors, shared by `PLL` and `PLL64`
+macro_rules! impl_pll {
+    ($pll:ty, $t:ty, $max_shift:literal) => {
+        impl $pll {
+            /// Update the PLL with a new phase sample. This needs to be called (sampled)
+            /// periodically. The signal's phase/frequency is reconstructed relative to the
+            /// sampling period.
+            ///
+            /// Args:
+            /// * `x`: New input phase sample or None if a sample has been missed.
+            ///   For missed samples the PLL free-runs on the last frequency estimate (holdover).
+            ///   See `LockDetector` for a holdover timeout.
+            /// * `shift_frequency`: Frequency error scaling. The frequency gain per update is
+            ///   `1/(1 << shift_frequency)`.
+            /// * `shift_phase`: Phase error scaling. The phase gain is `1/(1 << shift_phase)`
+            ///   per update. A good value is typically `shift_frequency - 1`.
+            ///
+            /// Returns:
+            /// A tuple of instantaneous phase and frequency estimates.
+            pub fn update(
+                &mut self,
+                x: Option<$t>,
+                shift_frequency: u32,
+                shift_phase: u32,
+            ) -> ($t, $t) {
+                debug_assert!((1..=$max_shift).contains(&shift_frequency));
+                debug_assert!((1..=$max_shift).contains(&shift_phase));
+                if let Some(x) = x {
+                    let df = ((1 as $t) << (shift_frequency - 1))
+                        .wrapping_add(x)
+                        .wrapping_sub(self.x)
+                        .wrapping_sub(self.f)
+                        >> shift_frequency;
+                    self.x = x;
+                    self.f = self.f.wrapping_add(df);
+                    let f = self.f.wrapping_sub(df >> 1);
+                    self.y = self.y.wrapping_add(f);
+                    let dy = ((1 as $t) << (shift_phase - 1))
+                        .wrapping_add(x)
+                        .wrapping_sub(self.y)
+                        >> shift_phase;
+                    self.y = self.y.wrapping_add(dy);
+                    let y = self.y.wrapping_sub(dy >> 1);
+                    (y, f.wrapping_add(dy))
+                } else {
+                    self.x = self.x.wrapping_add(self.f);
+                    self.y = self.y.wrapping_add(self.f);
+                    (self.y, self.f)
+                }
+            }
+
+            /// Return the current phase estimate
+            pub fn phase(&self) -> $t {
+                self.y
+            }
+
+            /// Return the current frequency estimate
+            pub fn frequency(&self) -> $t {
+                self.f
+            }
+        }
+    };
+}
+pub(crate) use impl_pll;
+
 /// Type-II, sampled phase, discrete time PLL
 ///
 /// This PLL tracks the frequency and phase of an input signal with respect to the sampling clock.
@@ -27,12 +92,12 @@ use serde::{Deserialize, Serialize};
 /// efficiently by dithering.
 ///
 /// This PLL does not unwrap phase slips accumulated during (frequency) lock acquisition.
-/// This can and should be implemented elsewhere by unwrapping and scaling the input phase
-/// and un-scaling and wrapping output phase and frequency. This then affects dynamic range,
+/// This is implemented by `UnwrappingPLL` by unwrapping and scaling the input phase
+/// and un-scaling the output phase and frequency. This then affects dynamic range,
 /// gain, and noise accordingly.
 ///
-/// The extension to I^3,I^2,I behavior to track chirps phase-accurately or to i64 data to
-/// increase resolution for extremely narrowband applications is obvious.
+/// See `PLL3` for the extension to I^3,I^2,I behavior to track chirps phase-accurately
+/// and `PLL64` for i64 data to increase resolution for extremely narrowband applications.
 #[derive(Copy, Clone, Default, Deserialize, Serialize)]
 pub struct PLL {
     // last input phase
@@ -43,54 +108,38 @@ pub struct PLL {
     y: i32,
 }
 
+impl_pll!(PLL, i32, 30);
+
 impl PLL {
-    /// Update the PLL with a new phase sample. This needs to be called (sampled) periodically.
-    /// The signal's phase/frequency is reconstructed relative to the sampling period.
+    /// Create a new PLL with preset phase and frequency estimates.
     ///
-    /// Args:
-    /// * `x`: New input phase sample or None if a sample has been missed.
-    /// * `shift_frequency`: Frequency error scaling. The frequency gain per update is
-    ///   `1/(1 << shift_frequency)`.
-    /// * `shift_phase`: Phase error scaling. The phase gain is `1/(1 << shift_phase)`
-    ///   per update. A good value is typically `shift_frequency - 1`.
+    /// Presetting the nominal frequency (and phase) speeds up acquisition.
     ///
-    /// Returns:
-    /// A tuple of instantaneous phase and frequency estimates.
-    pub fn update(&mut self, x: Option<i32>, shift_frequency: u32, shift_phase: u32) -> (i32, i32) {
-        debug_assert!((1..=30).contains(&shift_frequency));
-        debug_assert!((1..=30).contains(&shift_phase));
-        if let Some(x) = x {
-            let df = (1i32 << (shift_frequency - 1))
-                .wrapping_add(x)
-                .wrapping_sub(self.x)
-                .wrapping_sub(self.f)
-                >> shift_frequency;
-            self.x = x;
-            self.f = self.f.wrapping_add(df);
-            let f = self.f.wrapping_sub(df >> 1);
-            self.y = self.y.wrapping_add(f);
-            let dy = (1i32 << (shift_phase - 1))
-                .wrapping_add(x)
-                .wrapping_sub(self.y)
-                >> shift_phase;
-            self.y = self.y.wrapping_add(dy);
-            let y = self.y.wrapping_sub(dy >> 1);
-            (y, f.wrapping_add(dy))
-        } else {
-            self.x = self.x.wrapping_add(self.f);
-            self.y = self.y.wrapping_add(self.f);
-            (self.y, self.f)
+    /// Args:
+    /// * `phase`: Initial phase estimate. This is also taken as the last input phase.
+    /// * `frequency`: Initial frequency estimate.
+    pub fn new(phase: i32, frequency: i32) -> Self {
+        Self {
+            x: phase,
+            f: frequency,
+            y: phase,
         }
     }
 
-    /// Return the current phase estimate
-    pub fn phase(&self) -> i32 {
-        self.y
+    /// Set the phase estimate. This is also taken as the last input phase.
+    pub fn set_phase(&mut self, phase: i32) {
+        self.x = phase;
+        self.y = phase;
+    }
+
+    /// Set the frequency estimate.
+    pub fn set_frequency(&mut self, frequency: i32) {
+        self.f = frequency;
     }
 
-    /// Return the current frequency estimate
-    pub fn frequency(&self) -> i32 {
-        self.f
+    /// Reset the PLL to zero phase and frequency.
+    pub fn reset(&mut self) {
+        *self = Self::default();
     }
 }
 
@@ -105,23 +154,46 @@ mod tests {
         assert_eq!(f, 0x1078);
     }
 
+    #[test]
+    fn preset() {
+        let f0 = 0x71f63049_i32;
+        let x0 = 0x1234_5678_i32;
+        let mut p = PLL::new(x0, f0);
+        let mut x = x0;
+        for _ in 0..1 << 10 {
+            x = x.wrapping_add(f0);
+            let (y, f) = p.update(Some(x), 10, 9);
+            // Locked from the start
+            assert_eq!(y, x);
+            assert_eq!(f, f0);
+        }
+        p.reset();
+        assert_eq!((p.phase(), p.frequency()), (0, 0));
+        p.set_phase(x0);
+        p.set_frequency(f0);
+        assert_eq!(
+            p.update(Some(x0.wrapping_add(f0)), 10, 9),
+            (x0.wrapping_add(f0), f0)
+        );
+    }
+
     #[test]
     fn converge() {
         let mut p = PLL::default();
         let f0 = 0x71f63049_i32;
         let shift = (10, 9);
-        let n = 31 << shift.0 + 2;
+        let n = 31 << (shift.0 + 2);
         let mut x = 0i32;
         for i in 0..n {
             x = x.wrapping_add(f0);
             let (y, f) = p.update(Some(x), shift.0, shift.1);
             if i > n / 4 {
                 // The remaining error would be removed by dithering.
-                assert_eq!(f.wrapping_sub(f0).abs() <= 1 << 10, true);
+                assert!(f.wrapping_sub(f0).abs() <= 1 << 10);
             }
             if i > n / 2 {
                 // The remaining error would be removed by dithering.
-                assert_eq!(y.wrapping_sub(x).abs() < 1 << 18, true);
+                assert!(y.wrapping_sub(x).abs() < 1 << 18);
             }
         }
     }
@@ -0,0 +1,155 @@
+use serde::{Deserialize, Serialize};
+
+/// Type-III, sampled phase, discrete time PLL
+///
+/// This PLL tracks the phase, frequency, and chirp rate (frequency change per update)
+/// of an input signal with respect to the sampling clock. The open loop transfer function
+/// is I^3,I^2,I from input phase to output phase. A linear frequency ramp (chirp) is
+/// tracked without steady state phase or frequency error.
+///
+/// The frequency and chirp rate are estimated by a P,I loop on the frequency error
+/// (the difference between the measured frequency, i.e. the input phase difference,
+/// and the predicted frequency). The phase is tracked by a P loop with the frequency
+/// estimate as feed-forward.
+///
+/// As for `PLL`, all math is naturally wrapping 32 bit integer, the gains are powers of
+/// two given as shifts, and the PLL locks to the alias in the first Nyquist zone.
+/// The frequency/chirp loop is well damped for `shift_chirp = 2*shift_frequency + 1`
+/// (critically damped for `2*shift_frequency + 2`). A good phase gain is typically
+/// `shift_phase = shift_frequency - 1`.
+///
+/// Unlike `PLL`, the residues of the gain shifts are kept and fed back (first order error
+/// feedback). There is no truncation deadband and no phase offset for low gains.
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub struct PLL3 {
+    // last input phase
+    x: i32,
+    // filtered chirp rate
+    c: i32,
+    // filtered frequency
+    f: i32,
+    // filtered output phase
+    y: i32,
+    // chirp, frequency, and phase gain residues
+    r: [i32; 3],
+}
+
+// Scale by `1/(1 << shift)` with error feedback of the residue `r`.
+fn scale(r: &mut i32, e: i32, shift: u32) -> i32 {
+    let t = *r as i64 + e as i64;
+    let d = t >> shift;
+    *r = (t - (d << shift)) as _;
+    d as _
+}
+
+impl PLL3 {
+    /// Update the PLL with a new phase sample. This needs to be called (sampled) periodically.
+    /// The signal's phase/frequency/chirp rate are reconstructed relative to the sampling period.
+    ///
+    /// Args:
+    /// * `x`: New input phase sample or None if a sample has been missed.
+    ///   Missed samples are extrapolated using the chirp rate and frequency estimates.
+    /// * `shift_chirp`: Chirp rate gain. The chirp rate gain per update is
+    ///   `1/(1 << shift_chirp)`.
+    /// * `shift_frequency`: Frequency gain. The frequency gain per update is
+    ///   `1/(1 << shift_frequency)`.
+    /// * `shift_phase`: Phase gain. The phase gain per update is `1/(1 << shift_phase)`.
+    ///
+    /// Returns:
+    /// A tuple of instantaneous phase and frequency estimates.
+    pub fn update(
+        &mut self,
+        x: Option<i32>,
+        shift_chirp: u32,
+        shift_frequency: u32,
+        shift_phase: u32,
+    ) -> (i32, i32) {
+        debug_assert!((1..=30).contains(&shift_chirp));
+        debug_assert!((1..=30).contains(&shift_frequency));
+        debug_assert!((1..=30).contains(&shift_phase));
+        // Predicted frequency
+        let f = self.f.wrapping_add(self.c);
+        if let Some(x) = x {
+            // Frequency error
+            let e = x.wrapping_sub(self.x).wrapping_sub(f);
+            self.x = x;
+            let dc = scale(&mut self.r[0], e, shift_chirp);
+            self.c = self.c.wrapping_add(dc);
+            let df = scale(&mut self.r[1], e, shift_frequency);
+            self.f = f.wrapping_add(df);
+            self.y = self.y.wrapping_add(self.f);
+            let dy = scale(&mut self.r[2], x.wrapping_sub(self.y), shift_phase);
+            self.y = self.y.wrapping_add(dy);
+        } else {
+            self.f = f;
+            self.x = self.x.wrapping_add(f);
+            self.y = self.y.wrapping_add(f);
+        }
+        (self.y, self.f)
+    }
+
+    /// Return the current phase estimate
+    pub fn phase(&self) -> i32 {
+        self.y
+    }
+
+    /// Return the current frequency estimate
+    pub fn frequency(&self) -> i32 {
+        self.f
+    }
+
+    /// Return the current chirp rate estimate
+    pub fn chirp(&self) -> i32 {
+        self.c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PLL;
+
+    #[test]
+    fn chirp() {
+        let mut p = PLL3::default();
+        let mut p2 = PLL::default();
+        let (c0, mut f0) = (0x1234, -0x3456_789a_i32);
+        let shift = (17, 8, 7);
+        let n = 1 << 20;
+        let mut x = 0i32;
+        let mut e2 = 0;
+        for i in 0..n {
+            f0 = f0.wrapping_add(c0);
+            x = x.wrapping_add(f0);
+            // A few missed samples
+            let xi = if i % 10_000 < 3 { None } else { Some(x) };
+            let (y, f) = p.update(xi, shift.0, shift.1, shift.2);
+            let (y2, _) = p2.update(Some(x), shift.1, shift.2);
+            if i > n / 2 {
+                assert_eq!(y, x);
+                assert_eq!(f, f0);
+                assert_eq!(p.chirp(), c0);
+                e2 = y2.wrapping_sub(x);
+            }
+        }
+        // The type-II PLL lags
+        assert!(e2.abs() > 1 << 20);
+    }
+
+    #[test]
+    fn converge() {
+        let mut p = PLL3::default();
+        let f0 = 0x71f63049_i32;
+        let shift = (21, 10, 9);
+        let n = 1 << 18;
+        let mut x = 0i32;
+        for i in 0..n {
+            x = x.wrapping_add(f0);
+            let (y, f) = p.update(Some(x), shift.0, shift.1, shift.2);
+            if i > n / 2 {
+                assert_eq!(f, f0);
+                assert_eq!(y, x);
+            }
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use super::pll::impl_pll;
+use serde::{Deserialize, Serialize};
+
+/// Type-II, sampled phase, discrete time PLL with 64 bit resolution
+///
+/// This is the same PLL as `PLL` with `i64` phase and frequency. A full turn is `1 << 64`
+/// instead of `1 << 32`. The additional 32 bits of resolution reduce the truncation
+/// phase offset for low gains and allow shifts up to 62 for extremely narrowband
+/// applications.
+///
+/// All math is naturally wrapping 64 bit integer. Phase and frequency are understood modulo
+/// that overflow in the first Nyquist zone. Use `widen()` and `narrow()` to convert
+/// from and to the `i32` phase and frequency convention of `PLL`.
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub struct PLL64 {
+    // last input phase
+    x: i64,
+    // filtered frequency
+    f: i64,
+    // filtered output phase
+    y: i64,
+}
+
+impl_pll!(PLL64, i64, 62);
+
+impl PLL64 {
+    /// Convert an `i32` phase or frequency (full turn `1 << 32`) to the `PLL64` convention.
+    pub fn widen(x: i32) -> i64 {
+        (x as i64) << 32
+    }
+
+    /// Round a phase or frequency to the `i32` convention (full turn `1 << 32`).
+    ///
+    /// Rounding is "half up" and wraps.
+    pub fn narrow(x: i64) -> i32 {
+        (((x >> 31) + 1) >> 1) as _
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PLL;
+
+    #[test]
+    fn narrow() {
+        for x in [0, 1, -1, 0x1234_5678, i32::MIN, i32::MAX].iter() {
+            assert_eq!(PLL64::narrow(PLL64::widen(*x)), *x);
+        }
+        assert_eq!(PLL64::narrow(i64::MAX), i32::MIN);
+        assert_eq!(PLL64::narrow((1 << 31) - 1), 0);
+        assert_eq!(PLL64::narrow(1 << 31), 1);
+        assert_eq!(PLL64::narrow(-(1 << 31)), 0);
+    }
+
+    #[test]
+    fn converge() {
+        let mut p = PLL64::default();
+        let mut p32 = PLL::default();
+        // Frequency with sub-LSB resolution in the i32 convention
+        let f0 = 0x71f6_3049_9a3c_d051_i64;
+        let shift = (14, 13);
+        let n = 31 << (shift.0 + 2);
+        let mut x = 0i64;
+        let (mut e, mut e32) = (0i32, 0i32);
+        for i in 0..n {
+            x = x.wrapping_add(f0);
+            let (y, f) = p.update(Some(x), shift.0, shift.1);
+            let (y32, _) = p32.update(Some(PLL64::narrow(x)), shift.0, shift.1);
+            if i > n / 4 {
+                assert!(PLL64::narrow(f.wrapping_sub(f0)).abs() <= 1);
+            }
+            if i > n / 2 {
+                e = e.max(PLL64::narrow(y).wrapping_sub(PLL64::narrow(x)).abs());
+                e32 = e32.max(y32.wrapping_sub(PLL64::narrow(x)).abs());
+            }
+        }
+        // The i32 PLL has a truncation phase offset
+        assert!(e <= 1);
+        assert!(e32 > 1 << 10);
+    }
+
+    #[test]
+    fn narrowband() {
+        // Free running at the final frequency after lock, extremely low gain
+        let mut p = PLL64::default();
+        let f0 = 0x0123_4567_89ab_cdef_i64;
+        let mut x = 0i64;
+        for _ in 0..1 << 14 {
+            x = x.wrapping_add(f0);
+            p.update(Some(x), 8, 7);
+        }
+        for i in 0..1 << 10 {
+            x = x.wrapping_add(f0);
+            let (y, f) = p.update(Some(x), 60, 59);
+            // The residual frequency error from acquisition is no longer corrected
+            assert!(f.wrapping_sub(f0).abs() <= 1 << 8);
+            // The phase loop compensated the frequency error with a phase offset
+            assert!(y.wrapping_sub(x).abs() <= (1 << 15) + (i << 8));
+        }
+    }
+}
@@ -7,11 +7,11 @@
 /// `u32::MAX` corresponding to both being equal.
 #[derive(Copy, Clone, Default)]
 pub struct RPLL {
-    dt2: u32, // 1 << dt2 is the counter rate to update() rate ratio
-    x: i32,   // previous timestamp
-    ff: u32,  // current frequency estimate from frequency loop
-    f: u32,   // current frequency estimate from both frequency and phase loop
-    y: i32,   // current phase estimate
+    pub(crate) dt2: u32, // 1 << dt2 is the counter rate to update() rate ratio
+    x: i32,              // previous timestamp
+    ff: u32,             // current frequency estimate from frequency loop
+    f: u32,              // current frequency estimate from both frequency and phase loop
+    y: i32,              // current phase estimate
 }
 
 impl RPLL {
@@ -29,11 +29,29 @@ impl RPLL {
         }
     }
 
+    /// Create a new RPLL instance with preset phase and frequency estimates.
+    ///
+    /// Presetting the nominal frequency (and phase) speeds up acquisition,
+    /// see `set_phase()` and `set_frequency()`.
+    ///
+    /// Args:
+    /// * dt2: inverse update() rate, see `new()`.
+    /// * phase: Initial phase estimate.
+    /// * frequency: Initial frequency estimate of both the frequency and the phase loop.
+    pub fn new_preset(dt2: u32, phase: i32, frequency: u32) -> Self {
+        let mut rpll = Self::new(dt2);
+        rpll.set_phase(phase);
+        rpll.set_frequency(frequency);
+        rpll
+    }
+
     /// Advance the RPLL and optionally supply a new timestamp.
     ///
     /// Args:
     /// * input: Optional new timestamp (wrapping around at the i32 boundary).
     ///   There can be at most one timestamp per `update()` cycle (1 << dt2 counter cycles).
+    ///   Without timestamps the RPLL free-runs on the last frequency estimate (holdover).
+    ///   See `LockDetector` for a holdover timeout.
     /// * shift_frequency: Frequency lock settling time. 1 << shift_frequency is
     ///   frequency lock settling time in counter periods. The settling time must be larger
     ///   than the signal period to lock to.
@@ -88,6 +106,28 @@ impl RPLL {
     pub fn frequency(&self) -> u32 {
         self.f
     }
+
+    /// Set the phase estimate.
+    ///
+    /// The last timestamp is kept. It is in counter cycles and not derived from
+    /// the phase. The next timestamp is compared to it to update the frequency loop.
+    pub fn set_phase(&mut self, phase: i32) {
+        self.y = phase;
+    }
+
+    /// Set the frequency estimate of both the frequency and the phase loop.
+    ///
+    /// Presetting the nominal frequency speeds up acquisition. Note that the first
+    /// timestamp is compared to the last timestamp (zero after `new()` or `reset()`).
+    pub fn set_frequency(&mut self, frequency: u32) {
+        self.ff = frequency;
+        self.f = frequency;
+    }
+
+    /// Reset the RPLL to zero phase and frequency. The update rate `dt2` is kept.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.dt2);
+    }
 }
 
 #[cfg(test)]
@@ -97,6 +137,40 @@ mod test {
     use rand::{prelude::*, rngs::StdRng};
     use std::vec::Vec;
 
+    #[test]
+    fn preset() {
+        let dt2 = 8;
+        let period = 333;
+        // Nominal update rate relative to the reference frequency
+        let f0 = ((1u64 << (32 + dt2)) / period) as u32;
+        let mut p = RPLL::new_preset(dt2, 0, f0);
+        let mut p0 = RPLL::new(dt2);
+        let mut time = 0i32;
+        let mut next = period as i32;
+        for _ in 0..100 {
+            let x = if time.wrapping_sub(next) >= 0 {
+                next = next.wrapping_add(period as i32);
+                Some(next.wrapping_sub(period as i32))
+            } else {
+                None
+            };
+            p.update(x, 12, 11);
+            p0.update(x, 12, 11);
+            time = time.wrapping_add(1 << dt2);
+        }
+        // Faster acquisition
+        assert!((p.frequency().wrapping_sub(f0) as i32).abs() < 1 << 8);
+        assert!((p0.frequency().wrapping_sub(f0) as i32).abs() > 1 << 20);
+        p.set_phase(0x1234);
+        let f = p.frequency();
+        assert_eq!(
+            p.update(None, 12, 11),
+            (0x1234i32.wrapping_add(f as i32), f)
+        );
+        p.reset();
+        assert_eq!((p.phase(), p.frequency(), p.dt2), (0, 0, dt2));
+    }
+
     #[test]
     fn make() {
         let _ = RPLL::new(8);
@@ -132,7 +206,7 @@ mod test {
         fn run(&mut self, n: usize) -> (Vec<f32>, Vec<f32>) {
             assert!(self.period >= 1 << self.rpll.dt2);
             assert!(self.period < 1 << self.shift_frequency);
-            assert!(self.period < 1 << self.shift_phase + 1);
+            assert!(self.period < 1 << (self.shift_phase + 1));
 
             let mut y = Vec::<f32>::new();
             let mut f = Vec::<f32>::new();
@@ -156,7 +230,7 @@ mod test {
                 // phase error
                 y.push(yi.wrapping_sub(y_ref) as f32 / 2f32.powi(32));
 
-                let p_ref = 1 << 32 + self.rpll.dt2;
+                let p_ref = 1 << (32 + self.rpll.dt2);
                 let p_sig = fi as u64 * self.period as u64;
                 // relative frequency error
                 f.push(
@@ -170,8 +244,8 @@ mod test {
         }
 
         fn measure(&mut self, n: usize, limits: [f32; 4]) {
-            let t_settle = (1 << self.shift_frequency - self.rpll.dt2 + 4)
-                + (1 << self.shift_phase - self.rpll.dt2 + 4);
+            let t_settle = (1 << (self.shift_frequency - self.rpll.dt2 + 4))
+                + (1 << (self.shift_phase - self.rpll.dt2 + 4));
             self.run(t_settle);
 
             let (y, f) = self.run(n);
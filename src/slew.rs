@@ -0,0 +1,226 @@
+use miniconf::Miniconf;
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+/// Sample type for `Slew`.
+pub trait SlewSample: Copy + Default + PartialOrd {
+    /// Advance the limiter state `yv = [y, v]` by one sample toward `target`.
+    ///
+    /// See `Slew::update()`.
+    fn slew(yv: &mut [Self; 2], target: Self, rate: Self, accel: Self);
+}
+
+macro_rules! impl_slew_float {
+    ($T:ty) => {
+        impl SlewSample for $T {
+            fn slew(yv: &mut [Self; 2], target: Self, rate: Self, accel: Self) {
+                let [y, v] = *yv;
+                // Mirror such that the target is ahead
+                let (e, v, s) = if target < y {
+                    (y - target, -v, -1.)
+                } else {
+                    (target - y, v, 1.)
+                };
+                let v1 = if accel > 0. {
+                    // Largest velocity that still allows braking to a stop at
+                    // the target: v1 + (v1 - a) + (v1 - 2a) + .. <= e
+                    let brake = 0.5 * (Float::sqrt(accel * accel + 8. * accel * e) - accel);
+                    e.min(brake).min(v + accel).max(v - accel)
+                } else {
+                    e
+                };
+                let v1 = s * v1.max(-rate).min(rate);
+                *yv = [y + v1, v1];
+            }
+        }
+    };
+}
+impl_slew_float!(f32);
+impl_slew_float!(f64);
+
+// Floor of the square root
+fn isqrt(x: u128) -> u128 {
+    let mut r = 0;
+    let mut b = 1 << 126;
+    while b > x {
+        b >>= 2;
+    }
+    let mut x = x;
+    while b != 0 {
+        if x >= r + b {
+            x -= r + b;
+            r = (r >> 1) + b;
+        } else {
+            r >>= 1;
+        }
+        b >>= 2;
+    }
+    r
+}
+
+impl SlewSample for i32 {
+    fn slew(yv: &mut [Self; 2], target: Self, rate: Self, accel: Self) {
+        let [y, v] = *yv;
+        let (y, v, target, rate, accel) =
+            (y as i64, v as i64, target as i64, rate as i64, accel as i64);
+        let (e, v, s) = if target < y {
+            (y - target, -v, -1)
+        } else {
+            (target - y, v, 1)
+        };
+        let v1 = if accel > 0 {
+            // See the floating point implementation, rounded toward zero
+            let (a, d) = (accel as u128, e as u128);
+            let brake = (isqrt(a * a + 8 * a * d) - a) as i64 >> 1;
+            e.min(brake).min(v + accel).max(v - accel)
+        } else {
+            e
+        };
+        let v1 = s * v1.max(-rate).min(rate);
+        *yv = [(y + v1).max(i32::MIN as _).min(i32::MAX as _) as _, v1 as _];
+    }
+}
+
+/// Slew rate and acceleration limiter.
+///
+/// Ramps a signal (e.g. a setpoint or the `y_offset` of an IIR filter) toward
+/// a target with a maximum rate (change per sample) and a maximum acceleration
+/// (change of the rate per sample). With acceleration limiting the ramp
+/// brakes ahead of the target such that it neither overshoots nor steps.
+///
+/// The limiter state `[y, v]` contains the current output `y` and the current
+/// rate `v`.
+///
+/// # Miniconf
+///
+/// `{"rate": rate, "accel": accel}`
+///
+/// * `rate` is the maximum rate. Must be positive.
+/// * `accel` is the maximum acceleration. Zero for unlimited acceleration.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Deserialize, Serialize, Miniconf)]
+pub struct Slew<T> {
+    pub rate: T,
+    pub accel: T,
+}
+
+impl<T: SlewSample> Slew<T> {
+    pub fn new(rate: T, accel: T) -> Self {
+        Self { rate, accel }
+    }
+
+    /// Update the limiter with the current target and return the new output.
+    /// Only the state `yv` is modified.
+    ///
+    /// # Arguments
+    /// * `yv` - Current limiter state.
+    /// * `target` - Target value.
+    pub fn update(&self, yv: &mut [T; 2], target: T) -> T {
+        T::slew(yv, target, self.rate, self.accel);
+        yv[0]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ramp() {
+        let s = Slew::new(2., 0.25);
+        let mut yv = [0., 0.];
+        let mut v = 0f64;
+        let mut n = 0;
+        while yv[0] != 100. {
+            let y = s.update(&mut yv, 100.);
+            assert!(y <= 100.);
+            assert!(yv[1] <= 2.);
+            assert!((yv[1] - v).abs() <= 0.25 + 1e-12);
+            v = yv[1];
+            n += 1;
+        }
+        // Trapezoid: 8 samples accelerating and braking each, 42 at full rate
+        assert!((55..=62).contains(&n));
+        assert_eq!(s.update(&mut yv, 100.), 100.);
+        assert_eq!(yv[1], 0.);
+        // Reverse while moving
+        for _ in 0..10 {
+            s.update(&mut yv, 0.);
+        }
+        assert!(yv[1] < 0.);
+        v = yv[1];
+        for _ in 0..1000 {
+            let y = s.update(&mut yv, 50.);
+            assert!((yv[1] - v).abs() <= 0.25 + 1e-12);
+            assert!(y >= 50.);
+            v = yv[1];
+        }
+        assert_eq!(yv, [50., 0.]);
+        // Unlimited acceleration
+        let s = Slew::new(3f32, 0.);
+        let mut yv = [0., 0.];
+        assert_eq!(s.update(&mut yv, -10.), -3.);
+        for _ in 0..3 {
+            s.update(&mut yv, -10.);
+        }
+        assert_eq!(yv, [-10., -1.]);
+    }
+
+    #[test]
+    fn int() {
+        let s = Slew::new(1 << 20, 1 << 12);
+        let mut yv = [i32::MIN, 0];
+        let mut v = 0;
+        let mut n = 0;
+        while yv[0] != i32::MAX {
+            s.update(&mut yv, i32::MAX);
+            assert!(yv[1] <= 1 << 20 && (yv[1] - v).abs() <= 1 << 12);
+            v = yv[1];
+            n += 1;
+        }
+        // Full rate for 2^32/2^20 samples plus acceleration and braking
+        assert!((4096 + 250..4096 + 262).contains(&n));
+        assert_eq!(s.update(&mut yv, i32::MAX), i32::MAX);
+        assert_eq!(yv[1], 0);
+        // Small steps are reached exactly
+        yv = [0, 0];
+        for target in [5, -3, 0, 10_000].iter() {
+            for _ in 0..100 {
+                s.update(&mut yv, *target);
+            }
+            let y = s.update(&mut yv, *target);
+            assert_eq!((y, yv[1]), (*target, 0));
+        }
+    }
+
+    #[test]
+    fn offset() {
+        use crate::iir::{self, Shape};
+        use crate::iir_int;
+        let mut f = iir::IIR::new(1., -1e3, 1e3);
+        f.set_lowpass(0.1, Shape::Q(0.7), 1.).unwrap();
+        let mut i = iir_int::IIR {
+            y_min: -1 << 30,
+            y_max: 1 << 30,
+            ..Default::default()
+        };
+        i.set_lowpass(0.1, Shape::Q(0.7), 1.).unwrap();
+        let (s, si) = (Slew::new(0.1, 0.01), Slew::new(1 << 20, 1 << 16));
+        let (mut v, mut vi) = (0., 0);
+        let (mut xy, mut xyi) = ([0.; 5], [0; 5]);
+        let (mut y, mut yi) = (0., 0);
+        for _ in 0..500 {
+            f.slew_y_offset(&s, &mut v, 10.);
+            i.slew_y_offset(&si, &mut vi, 1 << 28);
+            let y1 = f.update(&mut xy, 0., false);
+            let yi1 = i.update(&mut xyi, 0, false);
+            // The offset is amplified by the DC gain of the recursion
+            let g = 1. / (1. - f.ba[3] - f.ba[4]);
+            assert!((y1 - y).abs() <= 0.1 * g * 1.5);
+            assert!((yi1 - yi).abs() as f64 <= (1 << 20) as f64 * g * 1.5);
+            y = y1;
+            yi = yi1;
+        }
+        assert_eq!(f.y_offset, 10.);
+        assert_eq!(i.y_offset, 1 << 28);
+    }
+}
@@ -7,7 +7,7 @@ use super::Complex;
 /// # Args
 /// * `a` - First input.
 /// * `b` - Second input. The relative tolerance is computed with respect to the maximum of the
-/// absolute values of the first and second inputs.
+///   absolute values of the first and second inputs.
 /// * `rtol` - Relative tolerance.
 /// * `atol` - Fixed tolerance.
 ///
@@ -34,3 +34,60 @@ pub fn complex_allclose(a: &[Complex<f32>], b: &[Complex<f32>], rtol: f32, atol:
         .zip(b)
         .all(|(&i, &j)| complex_isclose(i, j, rtol, atol))
 }
+
+/// Deterministic first order plus dead time plant.
+///
+/// `y[n] = a*y[n - 1] + (1 - a)*k*u[n - 1 - delay]`
+pub struct Fopdt {
+    pub k: f64,
+    pub a: f64,
+    pub delay: usize,
+    y: f64,
+    u: [f64; 64],
+    n: usize,
+}
+
+impl Fopdt {
+    pub fn new(k: f64, a: f64, delay: usize) -> Self {
+        assert!(delay < 64);
+        Self {
+            k,
+            a,
+            delay,
+            y: 0.,
+            u: [0.; 64],
+            n: 0,
+        }
+    }
+
+    /// Current output.
+    pub fn output(&self) -> f64 {
+        self.y
+    }
+
+    /// Apply input `u` and advance by one sample.
+    pub fn update(&mut self, u: f64) -> f64 {
+        self.u[self.n % 64] = u;
+        let u = self.u[(self.n + 64 - self.delay) % 64];
+        self.n += 1;
+        self.y = self.a * self.y + (1. - self.a) * self.k * u;
+        self.y
+    }
+
+    /// Exact ultimate gain and period (in samples) by bisection of the phase.
+    pub fn ultimate(&self) -> (f64, f64) {
+        let (a, d) = (self.a, self.delay as f64);
+        let phase = |w: f64| -(d + 1.) * w - (a * w.sin()).atan2(1. - a * w.cos());
+        let (mut w0, mut w1) = (0., core::f64::consts::PI);
+        for _ in 0..100 {
+            let w = 0.5 * (w0 + w1);
+            if phase(w) > -core::f64::consts::PI {
+                w0 = w;
+            } else {
+                w1 = w;
+            }
+        }
+        let g = self.k * (1. - a) / Complex::new(1. - a * w0.cos(), a * w0.sin()).norm();
+        (1. / g, 2. * core::f64::consts::PI / w0)
+    }
+}
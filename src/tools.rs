@@ -41,13 +41,25 @@ where
         .fold(y0, |y, xa| y + xa)
 }
 
+// Multiply-accumulate `i32` vectors `x` and `a` with offset `y0` and `shift`
+// fractional bits of `a`.
+//
+// Rounding is "half up". The accumulator and the output saturate, see `macc_i64()`.
 pub fn macc_i32(y0: i32, x: &[i32], a: &[i32], shift: u32) -> i32 {
     // Rounding bias, half up
     let y0 = ((y0 as i64) << shift) + (1 << (shift - 1));
-    let y = x
-        .iter()
+    let y = macc_i64(y0, x, a, &[], &[]);
+    (y >> shift).max(i32::MIN as _).min(i32::MAX as _) as _
+}
+
+// Multiply-accumulate `i32` vectors `x` and `a` and the extra term vectors
+// `u` and `b` into the wide accumulator `y0`.
+//
+// The sum saturates. Rounding and shifting is up to the caller.
+pub fn macc_i64(y0: i64, x: &[i32], a: &[i32], u: &[i32], b: &[i32]) -> i64 {
+    x.iter()
         .zip(a)
+        .chain(u.iter().zip(b))
         .map(|(x, a)| *x as i64 * *a as i64)
-        .fold(y0, |y, xa| y + xa);
-    (y >> shift) as i32
+        .fold(y0, |y, xa| y.saturating_add(xa))
 }
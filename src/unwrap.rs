@@ -69,7 +69,7 @@ where
     pub fn update(&mut self, x: T) -> (T, i32) {
         let (dx, dw) = overflowing_sub(x, self.x);
         self.x = x;
-        self.w = self.w.wrapping_add(dw as i32);
+        self.w = self.w.wrapping_add(dw);
         (dx, self.w)
     }
 
@@ -130,7 +130,7 @@ mod tests {
             (0x100, 0, 1),
             (-1 << 31, 0, -1 << 23),
             (0x7fffffff, 0, 0x007f_ffff),
-            (0x7fffffff, 1, 0x0017f_ffff),
+            (0x7fffffff, 1, 0x017f_ffff),
             (-0x7fffffff, -1, -0x0180_0000),
             (0x1234_5600, 0x7f, 0x7f12_3456),
             (0x1234_5600, -0x7f, -0x7f00_0000 + 0x12_3456),
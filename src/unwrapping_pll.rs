@@ -0,0 +1,186 @@
+use super::{Unwrapper, PLL};
+use serde::{Deserialize, Serialize};
+
+/// PLL with input phase unwrapping and scaling
+///
+/// This combines an `Unwrapper` and a `PLL` to track the phase of a signal through the
+/// phase slips accumulated during lock acquisition or after frequency jumps.
+///
+/// The wrapped input phase (full turn `1 << 32`) is unwrapped to `i64` and scaled down by
+/// `1 << scale` before being fed to the `PLL`. The `PLL` therefore only slips a cycle
+/// once the phase error exceeds `1 << (scale - 1)` turns of the input instead of half
+/// a turn. The output phase is the unwrapped (`i64`) phase in units of the input.
+///
+/// The trade-offs of the input scaling are:
+/// * Range: Up to `1 << (scale - 1)` turns of phase error are tracked without slips.
+/// * Resolution: The `scale` lowest bits of the input phase are rounded away. The
+///   resulting quantization noise is `1 << scale` times larger than for the plain `PLL`
+///   and so is the truncation phase offset for low gains.
+/// * Gain and bandwidth: The loop is linear and the scaling is undone on the output.
+///   Gains, bandwidth, and settling times are those of `PLL` with the same shifts.
+///
+/// The unwrapping of the input requires the input phase to change by less than half
+/// a turn between updates (i.e. the signal must be in the first Nyquist zone).
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub struct UnwrappingPLL {
+    unwrapper: Unwrapper<i32>,
+    pll: PLL,
+    // log2 of the input scaling
+    scale: u32,
+    // last PLL output phase
+    y: i32,
+    // unwrapped output phase
+    y64: i64,
+}
+
+impl UnwrappingPLL {
+    /// Create a new PLL.
+    ///
+    /// Args:
+    /// * `scale`: The input phase is scaled down by `1 << scale` before being tracked.
+    ///   Valid values are `0..=31`.
+    pub fn new(scale: u32) -> Self {
+        debug_assert!(scale < 32);
+        Self {
+            scale,
+            ..Default::default()
+        }
+    }
+
+    /// Update the PLL with a new wrapped phase sample.
+    ///
+    /// Args:
+    /// * `x`: New input phase sample (full turn `1 << 32`) or None if a sample has been missed.
+    ///   For missed samples the unwrapper reference is advanced by the frequency estimate
+    ///   such that gaps of any length are unwrapped consistently with the holdover of the `PLL`.
+    /// * `shift_frequency`: Frequency gain, see `PLL::update()`.
+    /// * `shift_phase`: Phase gain, see `PLL::update()`.
+    ///
+    /// Returns:
+    /// A tuple of the unwrapped phase and the frequency estimates, both in units of the input.
+    pub fn update(&mut self, x: Option<i32>, shift_frequency: u32, shift_phase: u32) -> (i64, i64) {
+        let x = match x {
+            Some(x) => {
+                let (_, w) = self.unwrapper.update(x);
+                let x = ((w as i64) << 32) + x as i64;
+                // Rounding is "half up"
+                Some(((x + (1 << self.scale >> 1)) >> self.scale) as i32)
+            }
+            None => {
+                // Predict the input phase (wrapping, less than half a turn per update)
+                let f = self.frequency() as i32;
+                self.unwrapper
+                    .update(self.unwrapper.phase().wrapping_add(f));
+                None
+            }
+        };
+        let (y, f) = self.pll.update(x, shift_frequency, shift_phase);
+        self.y64 = self
+            .y64
+            .wrapping_add((y.wrapping_sub(self.y) as i64) << self.scale);
+        self.y = y;
+        (self.y64, (f as i64) << self.scale)
+    }
+
+    /// Return the current unwrapped phase estimate
+    pub fn phase(&self) -> i64 {
+        self.y64
+    }
+
+    /// Return the current frequency estimate
+    pub fn frequency(&self) -> i64 {
+        (self.pll.frequency() as i64) << self.scale
+    }
+
+    /// Return the current number of input phase wraps
+    pub fn wraps(&self) -> i32 {
+        self.unwrapper.wraps()
+    }
+
+    /// Return the underlying PLL tracking the scaled input phase
+    pub fn pll(&self) -> &PLL {
+        &self.pll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slips() {
+        // Acquisition from zero frequency
+        let f0 = 0x1234_5678_i32;
+        let shift = (10, 9);
+        let n = 1 << 14;
+        let mut p = UnwrappingPLL::new(8);
+        let mut p32 = PLL::default();
+        let (mut x, mut x64) = (0i32, 0i64);
+        // The plain PLL output phase, unwrapped
+        let (mut y32, mut y64) = (0i32, 0i64);
+        for i in 0..n {
+            x = x.wrapping_add(f0);
+            x64 += f0 as i64;
+            let (y, f) = p.update(Some(x), shift.0, shift.1);
+            let (y1, _) = p32.update(Some(x), shift.0, shift.1);
+            y64 += y1.wrapping_sub(y32) as i64;
+            y32 = y1;
+            if i > 3 * n / 4 {
+                assert!((f - f0 as i64).abs() <= 1 << 8);
+                // The truncation phase offset is scaled up as well
+                assert!((y - x64).abs() <= 1 << 28);
+            }
+        }
+        assert_eq!(p.wraps() as i64, (x64 - x as i64) >> 32);
+        // The plain PLL has locked modulo full turns but has slipped cycles
+        assert!(y32.wrapping_sub(x).abs() <= 1 << 20);
+        assert!((y64 - x64).abs() >= 1 << 32);
+    }
+
+    #[test]
+    fn missing() {
+        let f0 = -0x0765_4321_i32;
+        let mut p = UnwrappingPLL::new(4);
+        let mut x = 0x4000_0000_i32;
+        let mut x64 = x as i64;
+        for i in 0..1 << 14 {
+            x = x.wrapping_add(f0);
+            x64 += f0 as i64;
+            let xi = if i % 100 < 10 { None } else { Some(x) };
+            let (y, f) = p.update(xi, 8, 7);
+            if i > 1 << 13 {
+                assert!((f - f0 as i64).abs() <= 1 << 8);
+                assert!((y - x64).abs() <= 1 << 16);
+            }
+        }
+    }
+
+    #[test]
+    fn gap() {
+        let f0 = 0x0765_4321_i32;
+        let mut p = UnwrappingPLL::new(4);
+        let (mut x, mut x64) = (0i32, 0i64);
+        for i in 0..1 << 14 {
+            x = x.wrapping_add(f0);
+            x64 += f0 as i64;
+            // Gap of several turns
+            let xi = if (1 << 13..(1 << 13) + 1000).contains(&i) {
+                None
+            } else {
+                Some(x)
+            };
+            let (y, f) = p.update(xi, 8, 7);
+            if i > 1 << 12 {
+                // The residual frequency error accumulates during the gap but there are
+                // no slips
+                assert!((y - x64).abs() <= 1 << 24);
+            }
+            if i > (1 << 13) + 4000 {
+                assert!((f - f0 as i64).abs() <= 1 << 8);
+                // The truncation phase offset is scaled up
+                assert!((y - x64).abs() <= 1 << 20);
+            }
+        }
+        assert_eq!(p.wraps() as i64, (x64 - x as i64) >> 32);
+    }
+}